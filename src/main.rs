@@ -1,31 +1,64 @@
-use mckerel_protocol;
-use mckerel_protocol::de::Deserialize;
+use mckerel_protocol::states::{Conn, HandshakeOrLegacyPing, NextState};
+use mckerel_protocol::states::login::{self, LoginConfig};
+use mckerel_protocol::states::play::{self, JoinConfig};
+use mckerel_protocol::states::status::{self, ServerStatus, Favicon};
 use tokio::net::{TcpListener, TcpStream};
 
+fn server_status() -> ServerStatus {
+    let mut status = ServerStatus::new("A mckerel server", 20, 0);
+    // same place vanilla looks for it
+    if let Ok(png) = std::fs::read("server-icon.png") {
+        match Favicon::from_png(&png) {
+            Ok(favicon) => status.favicon = Some(favicon),
+            Err(err) => println!("couldn't use server-icon.png: {}", err),
+        }
+    }
+    status
+}
+
 async fn handle_connection(conn: TcpStream) {
     println!("got a connection");
 
-    let (send, mut recv) = mckerel_protocol::make_conn(conn);
-
-    let packet = match recv.read_packet_or_legacy_ping().await.unwrap() {
-        mckerel_protocol::PacketOrLegacyPing::Packet(p) => p.read_all().await.unwrap(),
-        mckerel_protocol::PacketOrLegacyPing::LegacyPing => {
+    let mut conn = Conn::new(conn);
+    let handshake = match conn.read_handshake().await {
+        Ok(HandshakeOrLegacyPing::Handshake(handshake)) => handshake,
+        Ok(HandshakeOrLegacyPing::LegacyPing(ping)) => {
             println!("got a legacy ping");
-            vec![0xfe]
-        }
+            if let Err(err) = status::respond_legacy(conn, &ping, &server_status()).await {
+                println!("couldn't answer a legacy ping: {}", err);
+            }
+            return;
+        },
+        // there's no way to tell a client that hasn't picked a state why
+        Err(err) => {
+            println!("bad handshake: {}", err);
+            return;
+        },
     };
-    println!("{:?}", packet);
+    println!("{} {}", handshake.version, handshake.address);
 
-    let mut content_deser = mckerel_protocol::de::ByteReader::new(&packet);
-    let packet_data = mckerel_protocol::packets::serverbound::handshake::Packet::deserialize(&mut content_deser).unwrap( );
-    if let mckerel_protocol::packets::serverbound::handshake::Packet::Handshake(packet_data) = packet_data {
-        println!("{} {}", packet_data.version, packet_data.address);
+    match conn.next_state(&handshake) {
+        NextState::Status(conn) => {
+            if let Err(err) = status::respond(conn, &server_status()).await {
+                println!("couldn't answer a status request: {}", err);
+            }
+        },
+        NextState::Login(conn) => {
+            // login kicks the client itself when it fails
+            let (conn, profile) = match login::login(conn, &LoginConfig::default()).await {
+                Ok(res) => res,
+                Err(err) => {
+                    println!("login failed: {}", err);
+                    return;
+                },
+            };
+            println!("{} logged in", profile.username);
+            match play::void_world(conn, &JoinConfig::default()).await {
+                Ok(()) => println!("{} left", profile.username),
+                Err(err) => println!("{} got kicked: {}", profile.username, err),
+            }
+        },
     }
-    else {
-        println!("not the right kind of packet i guess");
-    }
-
-    send.shutdown();
 }
 
 #[tokio::main]
@@ -0,0 +1,104 @@
+use std::time::{Duration, Instant};
+use criterion::{criterion_group, criterion_main, Criterion};
+use mckerel_protocol::{Send, Recv};
+use mckerel_protocol::de::{self, Deserialize};
+use mckerel_protocol::ser::{Serialize, ByteWriter};
+use mckerel_protocol::packets::{serverbound, clientbound};
+use mckerel_protocol::serverbound::play::KeepAlive;
+use mckerel_protocol::clientbound::play::MapData;
+use tokio::runtime::Runtime;
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+async fn connect(compression: Option<usize>) -> (Send, Recv) {
+    let (server, client) = common::connect().await;
+    let (mut send, _) = mckerel_protocol::make_conn(client);
+    let (_, mut recv) = mckerel_protocol::make_conn(server);
+    if let Some(threshold) = compression {
+        send.enable_compression(threshold);
+        recv.enable_compression(threshold);
+    }
+    (send, recv)
+}
+
+type ReadFuture<'a> = std::pin::Pin<Box<dyn std::future::Future<Output = ()> + 'a>>;
+
+// how long it takes recv to get through iters copies of packet, written from another task
+// packet is already serialized, id included
+fn time_reads(runtime: &Runtime, iters: u64, compression: Option<usize>, packet: &[u8], read: for<'a> fn(&'a mut Recv) -> ReadFuture<'a>) -> Duration {
+    runtime.block_on(async {
+        let (mut send, mut recv) = connect(compression).await;
+        let packet = packet.to_vec();
+        let writer = tokio::spawn(async move {
+            for _ in 0..iters {
+                send.write_packet_bytes(&packet).await.unwrap();
+            }
+            send.flush().await.unwrap();
+            send
+        });
+        let start = Instant::now();
+        for _ in 0..iters {
+            read(&mut recv).await;
+        }
+        let elapsed = start.elapsed();
+        writer.await.unwrap();
+        elapsed
+    })
+}
+
+// the old way, a new Vec for every packet
+fn read_all<'a, P>(recv: &'a mut Recv) -> ReadFuture<'a>
+where
+    P: for<'de> Deserialize<'de, Value = P> + 'static,
+{
+    Box::pin(async move {
+        let data = recv.read_packet().await.unwrap().read_all().await.unwrap();
+        criterion::black_box(de::deserialize_all::<P>(&data).unwrap());
+    })
+}
+
+fn read_pooled<'a, P>(recv: &'a mut Recv) -> ReadFuture<'a>
+where
+    P: for<'de> Deserialize<'de, Value = P> + 'static,
+{
+    Box::pin(async move {
+        criterion::black_box(recv.recv_packet::<P>().await.unwrap());
+    })
+}
+
+// P is one of the packet enums, so the id gets read too
+fn bench_packet<P>(c: &mut Criterion, name: &str, packet: P)
+where
+    P: Serialize<Value = P> + for<'de> Deserialize<'de, Value = P> + 'static,
+{
+    let mut bytes = Vec::new();
+    P::serialize(&packet, &mut ByteWriter::new(&mut bytes));
+
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    let mut group = c.benchmark_group(name);
+    for (compression_name, compression) in [("plain", None), ("compressed", Some(256))] {
+        group.bench_function(format!("{}/read_all", compression_name), |b| {
+            b.iter_custom(|iters| time_reads(&runtime, iters, compression, &bytes, read_all::<P>))
+        });
+        group.bench_function(format!("{}/pooled", compression_name), |b| {
+            b.iter_custom(|iters| time_reads(&runtime, iters, compression, &bytes, read_pooled::<P>))
+        });
+    }
+    group.finish();
+}
+
+fn keep_alive(c: &mut Criterion) {
+    bench_packet(c, "keep_alive", serverbound::play::Packet::from(KeepAlive(0x1234_5678)));
+}
+
+fn map_data(c: &mut Criterion) {
+    bench_packet(c, "map_data", clientbound::play::Packet::from(MapData {
+        map_id: 1, scale: 0, locked: false, tracking_position: false, icons: None,
+        columns: 128, rows: Some(128), x: Some(0), z: Some(0),
+        data: Some((0..128 * 128).map(|i| (i % 61) as u8).collect()),
+    }));
+}
+
+criterion_group!(benches, keep_alive, map_data);
+criterion_main!(benches);
@@ -0,0 +1,536 @@
+// generates the packet modules from the specs in protocol/, one per enabled version feature
+// see protocol/1.18.1.toml for the format of full specs and protocol/1.17.1.toml for
+// specs that only list the differences to a base version, which get translated to it
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Path;
+use std::{env, fs};
+
+use serde::Deserialize;
+
+const STATES: [&str; 4] = ["handshake", "status", "login", "play"];
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Spec {
+    version: Version,
+    #[serde(default)]
+    states: BTreeMap<String, State>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Version {
+    name: String,
+    protocol: i32,
+    base: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct State {
+    serverbound: Direction,
+    clientbound: Direction,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct Direction {
+    // only in specs with a base
+    ids: BTreeMap<String, i32>,
+    // clientbound packets from the base version that this one doesn't have, or has
+    // in a form they can't be translated to, writing them is an error
+    missing: Vec<String>,
+    enums: BTreeMap<String, SimpleEnum>,
+    types: Vec<Item>,
+    packets: Vec<Item>,
+}
+
+// an enum_impl! enum
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SimpleEnum {
+    tag: String,
+    values: BTreeMap<String, i64>,
+}
+
+// a struct if it has fields (or nothing), a tagged enum if it has variants
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Item {
+    name: String,
+    id: Option<i32>,
+    doc: Option<String>,
+    fields: Option<Vec<Field>>,
+    tag_type: Option<String>,
+    variants: Option<Vec<Variant>>,
+    // also generate <name>Ref, which borrows its strings and bytes from the packet
+    #[serde(default)]
+    borrowed: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Variant {
+    name: String,
+    tag: i64,
+    doc: Option<String>,
+    #[serde(default)]
+    fields: Vec<Field>,
+}
+
+// fields without a name make a tuple struct
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+struct Field {
+    name: Option<String>,
+    #[serde(rename = "type")]
+    ty: String,
+    doc: Option<String>,
+    with: Option<String>,
+    len: Option<String>,
+    #[serde(default)]
+    optional: bool,
+    #[serde(default)]
+    rest: bool,
+    when: Option<String>,
+    switch: Option<String>,
+    max_len: Option<usize>,
+}
+
+struct Output {
+    code: String,
+    indent: usize,
+}
+
+impl Output {
+    fn line(&mut self, line: &str) {
+        if line.is_empty() {
+            self.code.push('\n');
+        } else {
+            writeln!(self.code, "{:indent$}{}", "", line, indent = self.indent * 4).unwrap();
+        }
+    }
+
+    fn open(&mut self, line: &str) {
+        self.line(line);
+        self.indent += 1;
+    }
+
+    fn close(&mut self, line: &str) {
+        self.indent -= 1;
+        self.line(line);
+    }
+
+    fn doc(&mut self, doc: &Option<String>) {
+        for line in doc.iter().flat_map(|doc| doc.lines()) {
+            self.line(&format!("// {}", line));
+        }
+    }
+}
+
+// the #[packet(...)] attribute for a field, if it needs one
+fn field_attrs(field: &Field) -> Option<String> {
+    let mut attrs = Vec::new();
+    for (name, value) in [("with", &field.with), ("len", &field.len), ("when", &field.when), ("switch", &field.switch)] {
+        if let Some(value) = value {
+            attrs.push(format!("{} = {:?}", name, value));
+        }
+    }
+    if field.optional {
+        attrs.push("optional".to_owned());
+    }
+    if field.rest {
+        attrs.push("rest".to_owned());
+    }
+    if let Some(max_len) = field.max_len {
+        attrs.push(format!("max_len = {}", max_len));
+    }
+    if attrs.is_empty() {
+        None
+    } else {
+        Some(format!("#[packet({})]", attrs.join(", ")))
+    }
+}
+
+// writes the fields after `opening`, which is the struct or variant up to its name
+fn write_fields(out: &mut Output, opening: &str, fields: &[Field], vis: &str, end: &str, context: &str) {
+    let named = fields.iter().filter(|field| field.name.is_some()).count();
+    if fields.is_empty() {
+        out.line(&format!("{}{}", opening, end));
+    } else if named == fields.len() {
+        out.open(&format!("{} {{", opening));
+        for (i, field) in fields.iter().enumerate() {
+            out.doc(&field.doc);
+            if let Some(attrs) = field_attrs(field) {
+                out.line(&attrs);
+            }
+            let comma = if i + 1 < fields.len() { "," } else { "" };
+            out.line(&format!("{}{}: {}{}", vis, field.name.as_ref().unwrap(), field.ty, comma));
+        }
+        let close = if end == ";" { "}" } else { "}," };
+        out.close(close);
+    } else if named == 0 {
+        let fields: Vec<_> = fields.iter().map(|field| {
+            assert!(field.doc.is_none(), "{}: unnamed fields can't have docs", context);
+            match field_attrs(field) {
+                Some(attrs) => format!("{} {}{}", attrs, vis, field.ty),
+                None => format!("{}{}", vis, field.ty),
+            }
+        }).collect();
+        out.line(&format!("{}({}){}", opening, fields.join(", "), end));
+    } else {
+        panic!("{}: either all fields need a name or none of them", context);
+    }
+}
+
+fn write_item(out: &mut Output, item: &Item, context: &str) {
+    let context = format!("{}::{}", context, item.name);
+    out.doc(&item.doc);
+    out.line("#[derive(Packet)]");
+    let mut attrs = Vec::new();
+    if let Some(id) = item.id {
+        attrs.push(format!("id = {:#04x}", id));
+    }
+    if let Some(tag_type) = &item.tag_type {
+        attrs.push(format!("tag_type = {:?}", tag_type));
+    }
+    if !attrs.is_empty() {
+        out.line(&format!("#[packet({})]", attrs.join(", ")));
+    }
+
+    match (&item.fields, &item.variants, &item.tag_type) {
+        (fields, None, None) => {
+            let fields = fields.as_deref().unwrap_or(&[]);
+            write_fields(out, &format!("pub struct {}", item.name), fields, "pub ", ";", &context);
+        },
+        (None, Some(variants), Some(_)) => {
+            out.open(&format!("pub enum {} {{", item.name));
+            for variant in variants {
+                out.doc(&variant.doc);
+                out.line(&format!("#[packet(tag = {})]", variant.tag));
+                write_fields(out, &variant.name, &variant.fields, "", ",", &format!("{}::{}", context, variant.name));
+            }
+            out.close("}");
+        },
+        _ => panic!("{}: needs either fields, or variants and a tag_type", context),
+    }
+    out.line("");
+
+    if item.borrowed {
+        write_borrowed(out, item, &context);
+    }
+}
+
+// the type a field has when it's borrowed, if it's one that can be
+fn borrowed_type(ty: &str) -> Option<&'static str> {
+    match ty {
+        "String" => Some("&'de str"),
+        "Vec<u8>" => Some("&'de [u8]"),
+        "Option<String>" => Some("Option<&'de str>"),
+        _ => None,
+    }
+}
+
+fn write_borrowed(out: &mut Output, item: &Item, context: &str) {
+    let fields = match (&item.fields, &item.variants) {
+        (Some(fields), None) => fields,
+        _ => panic!("{}: only structs with fields can be borrowed", context),
+    };
+    let borrowed: Vec<Field> = fields.iter().map(|field| match borrowed_type(&field.ty) {
+        Some(ty) => Field { ty: ty.to_owned(), ..field.clone() },
+        None => field.clone(),
+    }).collect();
+    if borrowed.iter().zip(fields).all(|(borrowed, field)| borrowed.ty == field.ty) {
+        panic!("{}: nothing to borrow, it needs a String or Vec<u8> field", context);
+    }
+
+    out.line(&format!("// {} borrowing from the bytes it's read from, to_owned() gives a {}", item.name, item.name));
+    out.line("#[derive(Packet)]");
+    let id = item.id.map(|id| format!("id = {:#04x}, ", id)).unwrap_or_default();
+    out.line(&format!("#[packet({}owned = {:?})]", id, item.name));
+    let name = format!("{}Ref", item.name);
+    write_fields(out, &format!("pub struct {}<'de>", name), &borrowed, "pub ", ";", &format!("{}Ref", context));
+    out.line("");
+}
+
+fn write_enum(out: &mut Output, name: &str, simple: &SimpleEnum) {
+    let mut values: Vec<_> = simple.values.iter().collect();
+    values.sort_by_key(|(_, tag)| **tag);
+    out.open(&format!("enum_impl!({} {} {{", simple.tag, name));
+    for (i, (value, tag)) in values.iter().enumerate() {
+        let comma = if i + 1 < values.len() { "," } else { "" };
+        out.line(&format!("{} = {}{}", value, tag, comma));
+    }
+    out.close("});");
+    out.line("");
+}
+
+fn write_direction(out: &mut Output, direction: &Direction, context: &str) {
+    for (name, simple) in &direction.enums {
+        write_enum(out, name, simple);
+    }
+    for item in &direction.types {
+        assert!(item.id.is_none(), "{}::{}: types can't have an id, put it in packets", context, item.name);
+        write_item(out, item, context);
+    }
+    for item in &direction.packets {
+        assert!(item.id.is_some(), "{}::{}: packets need an id", context, item.name);
+        write_item(out, item, context);
+    }
+
+    out.open("packets_impl!(Packet {");
+    for (i, item) in direction.packets.iter().enumerate() {
+        let comma = if i + 1 < direction.packets.len() { "," } else { "" };
+        out.line(&format!("{}{}", item.name, comma));
+    }
+    out.close("});");
+}
+
+fn state_kind(state: &str) -> &'static str {
+    match state {
+        "handshake" => "Handshaking",
+        "status" => "Status",
+        "login" => "Login",
+        "play" => "Play",
+        _ => unreachable!(),
+    }
+}
+
+// picks serverbound or clientbound out of a state
+type GetDirection = fn(&State) -> &Direction;
+
+fn directions() -> [(&'static str, GetDirection); 2] {
+    [("serverbound", |state| &state.serverbound), ("clientbound", |state| &state.clientbound)]
+}
+
+fn write_version_info(out: &mut Output, spec: &Spec) {
+    out.line(&format!("pub const PROTOCOL_VERSION: i32 = {};", spec.version.protocol));
+    out.line(&format!("pub const VERSION_NAME: &str = {:?};", spec.version.name));
+}
+
+fn write_canonical(out: &mut Output, spec: &Spec, module: &str, file: &str) {
+    for (state_name, state) in &spec.states {
+        for (direction_name, get) in directions() {
+            assert!(get(state).ids.is_empty(), "{}: {}.{} has ids, but there's no base version", file, state_name, direction_name);
+            assert!(get(state).missing.is_empty(), "{}: {}.{} has missing packets, but there's no base version", file, state_name, direction_name);
+        }
+    }
+
+    out.line(&format!("// generated from protocol/{}", file));
+    out.open(&format!("pub mod {} {{", module));
+    out.line("use super::*;");
+    out.line("");
+    write_version_info(out, spec);
+    out.line("");
+    out.line("pub(crate) static VERSION: crate::versions::Version = crate::versions::Version {");
+    out.line("    protocol: PROTOCOL_VERSION,");
+    out.line("    name: VERSION_NAME,");
+    out.line("    serverbound: crate::versions::unchanged,");
+    out.line("    clientbound: crate::versions::unchanged,");
+    out.line("};");
+    for (direction_name, get) in directions() {
+        out.line("");
+        out.open(&format!("pub mod {} {{", direction_name));
+        out.line("use super::*;");
+        for state_name in STATES {
+            let direction = match spec.states.get(state_name).map(get) {
+                Some(direction) if !direction.packets.is_empty() => direction,
+                _ => continue,
+            };
+            out.line("");
+            out.open(&format!("pub mod {} {{", state_name));
+            out.line("use super::*;");
+            out.line("");
+            write_direction(out, direction, &format!("{}::{}::{}", module, direction_name, state_name));
+            out.close("}");
+        }
+        out.close("}");
+    }
+    out.close("}");
+    out.line("");
+}
+
+fn find_packet<'a>(spec: &'a Spec, state: &str, direction: GetDirection, name: &str) -> Option<&'a Item> {
+    spec.states.get(state).and_then(|state| direction(state).packets.iter().find(|item| item.name == name))
+}
+
+// the translation between a version with a base and the base, see versions::Version
+fn write_translation(out: &mut Output, spec: &Spec, base: &Spec, base_module: &str, direction_name: &str, get: GetDirection, file: &str) -> bool {
+    // (state, id it's matched on, what to do with it)
+    let mut arms = Vec::new();
+    for (state_name, state) in &spec.states {
+        let direction = get(state);
+        let kind = state_kind(state_name);
+        let base_id = |name: &str| match find_packet(base, state_name, get, name) {
+            Some(item) => item.id.unwrap(),
+            None => panic!("{}: {}.{}.{} isn't in the base version", file, state_name, direction_name, name),
+        };
+        let translate = |to: String, name: &str| format!("{}.map_err(|err| err.in_packet({:?}, id))?", to, name);
+        let mut renumbered = Vec::new();
+        for (name, &id) in &direction.ids {
+            let base_id = base_id(name);
+            let (from, to) = if direction_name == "serverbound" { (id, base_id) } else { (base_id, id) };
+            arms.push((kind, from, translate(format!("crate::versions::renumber({:#04x}, &mut input)", to), name)));
+            renumbered.push(base_id);
+        }
+        for name in &direction.missing {
+            assert_eq!(direction_name, "clientbound", "{}: {}.{} can't have missing packets", file, state_name, direction_name);
+            arms.push((kind, base_id(name), translate("crate::versions::missing()".to_owned(), name)));
+        }
+        for item in &direction.packets {
+            let (id, base_id) = (item.id.unwrap(), base_id(&item.name));
+            let this = format!("{}::{}::{}", direction_name, state_name, item.name);
+            let base = format!("super::{}::{}", base_module, this);
+            let (from, to, from_type, to_type) = if direction_name == "serverbound" {
+                (id, base_id, this, base)
+            } else {
+                (base_id, id, base, this)
+            };
+            arms.push((kind, from, translate(format!("crate::versions::convert::<{}, {}>({:#04x}, &mut input)", from_type, to_type, to), &item.name)));
+            if id != base_id {
+                renumbered.push(base_id);
+            }
+        }
+        // anything without an arm gets read as the canonical packet with that id, which is
+        // only right if this version didn't move that packet, and nothing else took its id
+        if direction_name == "serverbound" {
+            for base_id in renumbered {
+                if !arms.iter().any(|&(arm_kind, from, _)| arm_kind == kind && from == base_id) {
+                    arms.push((kind, base_id, "crate::versions::unknown(id)?".to_owned()));
+                }
+            }
+        }
+    }
+    if arms.is_empty() {
+        return false;
+    }
+
+    out.open(&format!("fn translate_{}(state: crate::versions::StateKind, packet: &[u8]) -> crate::de::Result<Option<Vec<u8>>> {{", direction_name));
+    out.line("let mut input = crate::de::ByteReader::new(packet);");
+    out.line("let id = <VarInt as crate::de::Deserialize>::deserialize(&mut input)?;");
+    out.open("let translated = match (state, id) {");
+    for (kind, id, action) in arms {
+        out.line(&format!("(crate::versions::StateKind::{}, {:#04x}) => {},", kind, id, action));
+    }
+    out.line("_ => return Ok(None),");
+    out.close("};");
+    out.line("Ok(Some(translated))");
+    out.close("}");
+    out.line("");
+    true
+}
+
+fn write_diff(out: &mut Output, spec: &Spec, base: &Spec, base_module: &str, module: &str, file: &str) {
+    out.line(&format!("// generated from protocol/{}, translated to {}", file, base_module));
+    out.open(&format!("pub mod {} {{", module));
+    let unchanged = spec.states.values().all(|state| directions().iter().all(|(_, get)| {
+        let direction = get(state);
+        direction.ids.is_empty() && direction.missing.is_empty() && direction.enums.is_empty() && direction.types.is_empty() && direction.packets.is_empty()
+    }));
+    if !unchanged {
+        out.line("use super::*;");
+        out.line("");
+    }
+    write_version_info(out, spec);
+    for (direction_name, get) in directions() {
+        let changed: Vec<_> = STATES.iter()
+            .filter_map(|state_name| spec.states.get(*state_name).map(|state| (state_name, get(state))))
+            .filter(|(_, direction)| !direction.packets.is_empty() || !direction.types.is_empty() || !direction.enums.is_empty())
+            .collect();
+        if changed.is_empty() {
+            continue;
+        }
+        out.line("");
+        out.open(&format!("pub mod {} {{", direction_name));
+        out.line("use super::*;");
+        for (state_name, direction) in changed {
+            out.line("");
+            out.open(&format!("pub mod {} {{", state_name));
+            out.line("use super::*;");
+            out.line("// everything that didn't change");
+            out.line(&format!("use crate::packets::{}::{}::{}::*;", base_module, direction_name, state_name));
+            out.line("");
+            for (name, simple) in &direction.enums {
+                write_enum(out, name, simple);
+            }
+            for item in direction.types.iter().chain(&direction.packets) {
+                write_item(out, item, &format!("{}::{}::{}", module, direction_name, state_name));
+            }
+            out.close("}");
+        }
+        out.close("}");
+    }
+    out.line("");
+
+    let mut translators = Vec::new();
+    for (direction_name, get) in directions() {
+        if write_translation(out, spec, base, base_module, direction_name, get, file) {
+            translators.push(format!("translate_{}", direction_name));
+        } else {
+            translators.push("crate::versions::unchanged".to_owned());
+        }
+    }
+    out.line("pub(crate) static VERSION: crate::versions::Version = crate::versions::Version {");
+    out.line("    protocol: PROTOCOL_VERSION,");
+    out.line("    name: VERSION_NAME,");
+    out.line(&format!("    serverbound: {},", translators[0]));
+    out.line(&format!("    clientbound: {},", translators[1]));
+    out.line("};");
+    out.close("}");
+    out.line("");
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=protocol");
+
+    // the feature for protocol/1.18.1.toml is v1_18_1
+    let mut versions = Vec::new();
+    for entry in fs::read_dir("protocol").expect("couldn't read protocol/") {
+        let path = entry.unwrap().path();
+        let file = path.file_name().unwrap().to_str().unwrap().to_owned();
+        let name = match file.strip_suffix(".toml") {
+            Some(name) => name,
+            None => continue,
+        };
+        let module = format!("v{}", name.replace('.', "_"));
+        if env::var_os(format!("CARGO_FEATURE_{}", module.to_uppercase())).is_none() {
+            continue;
+        }
+
+        let spec: Spec = toml::from_str(&fs::read_to_string(&path).unwrap())
+            .unwrap_or_else(|err| panic!("protocol/{}: {}", file, err));
+        for state in spec.states.keys() {
+            assert!(STATES.contains(&state.as_str()), "protocol/{}: unknown state {}", file, state);
+        }
+        versions.push((spec, module, file));
+    }
+    // newest first
+    versions.sort_by_key(|(spec, _, _)| -spec.version.protocol);
+
+    // the full spec is the canonical version, the one the rest of the crate uses
+    let mut full = versions.iter().filter(|(spec, _, _)| spec.version.base.is_none());
+    let (canonical, canonical_module, canonical_file) = full.next()
+        .expect("no full protocol version enabled, enable one of the v* features without a base");
+    assert!(full.next().is_none(), "only one protocol version without a base can be enabled");
+
+    let mut out = Output { code: String::new(), indent: 0 };
+    for (spec, module, file) in &versions {
+        match &spec.version.base {
+            None => write_canonical(&mut out, spec, module, file),
+            Some(base) => {
+                assert_eq!(base, &canonical.version.name, "protocol/{}: the base version has to be {}", file, canonical_file);
+                write_diff(&mut out, spec, canonical, canonical_module, module, file);
+            },
+        }
+    }
+    out.line(&format!("pub use {}::*;", canonical_module));
+    out.line("");
+    out.line("// newest first");
+    let statics: Vec<_> = versions.iter().map(|(_, module, _)| format!("&{}::VERSION", module)).collect();
+    out.line(&format!("pub(crate) static VERSIONS: &[&crate::versions::Version] = &[{}];", statics.join(", ")));
+
+    let out_path = Path::new(&env::var_os("OUT_DIR").unwrap()).join("packets.rs");
+    fs::write(out_path, out.code).unwrap();
+}
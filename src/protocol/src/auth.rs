@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use tokio::io;
+use crate::states::login::{Profile, ProfileProperty};
+use crate::types::Uuid;
+
+pub const MOJANG_SESSION_SERVER: &str = "https://sessionserver.mojang.com";
+
+pub type AuthFuture<'a> = Pin<Box<dyn Future<Output = io::Result<Option<Profile>>> + std::marker::Send + 'a>>;
+
+// checks with a session server that a player really is who they say they are
+pub trait Authenticator: std::marker::Send + Sync {
+    // resolves to None if the player hasn't joined using this server hash
+    fn has_joined<'a>(&'a self, username: &'a str, server_hash: &'a str) -> AuthFuture<'a>;
+}
+
+impl<T: Authenticator + ?Sized> Authenticator for Arc<T> {
+    fn has_joined<'a>(&'a self, username: &'a str, server_hash: &'a str) -> AuthFuture<'a> {
+        (**self).has_joined(username, server_hash)
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct HasJoinedResponse {
+    // hex, without dashes
+    id: String,
+    name: String,
+    #[serde(default)]
+    properties: Vec<ProfileProperty>,
+}
+
+fn http_error(err: reqwest::Error) -> io::Error {
+    io::Error::other(format!("session server request failed: {}", err))
+}
+
+// talks to a session server over http, mojang's by default
+pub struct HttpAuthenticator {
+    base_url: String,
+    client: reqwest::Client,
+}
+
+impl HttpAuthenticator {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into(),
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+impl Default for HttpAuthenticator {
+    fn default() -> Self {
+        Self::new(MOJANG_SESSION_SERVER)
+    }
+}
+
+impl Authenticator for HttpAuthenticator {
+    fn has_joined<'a>(&'a self, username: &'a str, server_hash: &'a str) -> AuthFuture<'a> {
+        Box::pin(async move {
+            let url = format!("{}/session/minecraft/hasJoined", self.base_url.trim_end_matches('/'));
+            let resp = self.client.get(url)
+                .query(&[("username", username), ("serverId", server_hash)])
+                .send().await
+                .map_err(http_error)?;
+            // the session server answers with no content if the player hasn't joined
+            if resp.status() == reqwest::StatusCode::NO_CONTENT {
+                return Ok(None);
+            }
+            let resp: HasJoinedResponse = resp.error_for_status()
+                .map_err(http_error)?
+                .json().await
+                .map_err(http_error)?;
+
+            let uuid = u128::from_str_radix(&resp.id, 16)
+                .map(Uuid)
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "session server sent a bad uuid"))?;
+            Ok(Some(Profile {
+                uuid,
+                username: resp.name,
+                properties: resp.properties,
+            }))
+        })
+    }
+}
+
+// an in-process stand-in for the session server
+// join does what the client's request to the real session server would
+#[derive(Default)]
+pub struct MockAuthenticator {
+    joined: Mutex<HashMap<String, (String, Profile)>>,
+}
+
+impl MockAuthenticator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn join(&self, server_hash: &str, profile: Profile) {
+        let mut joined = self.joined.lock().unwrap();
+        joined.insert(profile.username.clone(), (server_hash.to_owned(), profile));
+    }
+}
+
+impl Authenticator for MockAuthenticator {
+    fn has_joined<'a>(&'a self, username: &'a str, server_hash: &'a str) -> AuthFuture<'a> {
+        let mut joined = self.joined.lock().unwrap();
+        let res = match joined.get(username) {
+            Some((hash, _)) if hash == server_hash => joined.remove(username).map(|(_, profile)| profile),
+            _ => None,
+        };
+        Box::pin(async move { Ok(res) })
+    }
+}
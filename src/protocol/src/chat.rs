@@ -0,0 +1,623 @@
+// chat components, the json text used for chat, disconnect reasons, the motd and so on
+// as a packet field a TextComponent is a json string
+use std::borrow::Cow;
+use std::fmt::{self, Display, Formatter};
+use serde::{Serialize, Deserialize};
+use crate::de::{self, ByteReader, ErrorKind};
+use crate::ser::{self, ByteWriter};
+
+// chat json can be longer than other strings
+pub const MAX_CHAT_LENGTH: usize = 262144;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Color {
+    Black,
+    DarkBlue,
+    DarkGreen,
+    DarkAqua,
+    DarkRed,
+    DarkPurple,
+    Gold,
+    Gray,
+    DarkGray,
+    Blue,
+    Green,
+    Aqua,
+    Red,
+    LightPurple,
+    Yellow,
+    White,
+    // #rrggbb, which needs 1.16 or newer
+    Rgb(u8, u8, u8),
+}
+
+impl Color {
+    // in the order of their legacy codes, 0 to f
+    const NAMED: [(Color, &'static str, u32); 16] = [
+        (Self::Black, "black", 0x000000),
+        (Self::DarkBlue, "dark_blue", 0x0000aa),
+        (Self::DarkGreen, "dark_green", 0x00aa00),
+        (Self::DarkAqua, "dark_aqua", 0x00aaaa),
+        (Self::DarkRed, "dark_red", 0xaa0000),
+        (Self::DarkPurple, "dark_purple", 0xaa00aa),
+        (Self::Gold, "gold", 0xffaa00),
+        (Self::Gray, "gray", 0xaaaaaa),
+        (Self::DarkGray, "dark_gray", 0x555555),
+        (Self::Blue, "blue", 0x5555ff),
+        (Self::Green, "green", 0x55ff55),
+        (Self::Aqua, "aqua", 0x55ffff),
+        (Self::Red, "red", 0xff5555),
+        (Self::LightPurple, "light_purple", 0xff55ff),
+        (Self::Yellow, "yellow", 0xffff55),
+        (Self::White, "white", 0xffffff),
+    ];
+
+    // 0-9 or a-f, the character after a § in legacy text
+    pub fn from_code(code: char) -> Option<Self> {
+        code.to_digit(16).map(|index| Self::NAMED[index as usize].0)
+    }
+
+    // rgb colors get the closest named color's code
+    pub fn code(self) -> char {
+        let index = Self::NAMED.iter().position(|(named, _, _)| *named == self.nearest_named()).unwrap();
+        std::char::from_digit(index as u32, 16).unwrap()
+    }
+
+    pub fn rgb(self) -> (u8, u8, u8) {
+        match self {
+            Self::Rgb(r, g, b) => (r, g, b),
+            named => {
+                let rgb = Self::NAMED.iter().find(|(color, _, _)| *color == named).unwrap().2;
+                ((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)
+            },
+        }
+    }
+
+    pub fn nearest_named(self) -> Self {
+        let (r, g, b) = self.rgb();
+        let distance = |color: Color| {
+            let (r2, g2, b2) = color.rgb();
+            let d = |a: u8, b: u8| (i32::from(a) - i32::from(b)).pow(2);
+            d(r, r2) + d(g, g2) + d(b, b2)
+        };
+        Self::NAMED.iter().map(|(color, _, _)| *color).min_by_key(|color| distance(*color)).unwrap()
+    }
+
+    // a name like "dark_red" or #rrggbb
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.strip_prefix('#') {
+            Some(hex) if hex.len() == 6 => {
+                let rgb = u32::from_str_radix(hex, 16).ok()?;
+                Some(Self::Rgb((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8))
+            },
+            Some(_) => None,
+            None => Self::NAMED.iter().find(|(_, named, _)| *named == name).map(|(color, _, _)| *color),
+        }
+    }
+}
+
+impl Display for Color {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Rgb(r, g, b) => write!(f, "#{:02x}{:02x}{:02x}", r, g, b),
+            named => {
+                let name = Self::NAMED.iter().find(|(color, _, _)| color == named).unwrap().1;
+                write!(f, "{}", name)
+            },
+        }
+    }
+}
+
+impl Serialize for Color {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = Cow::<str>::deserialize(deserializer)?;
+        Self::parse(&name).ok_or_else(|| serde::de::Error::invalid_value(serde::de::Unexpected::Str(&name), &"a color"))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", content = "value", rename_all = "snake_case")]
+pub enum ClickEvent {
+    OpenUrl(String),
+    RunCommand(String),
+    SuggestCommand(String),
+    // the page number of a book, as a string
+    ChangePage(String),
+    CopyToClipboard(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", content = "contents", rename_all = "snake_case")]
+pub enum HoverEvent {
+    ShowText(Box<TextComponent>),
+    ShowItem(HoverItem),
+    ShowEntity(HoverEntity),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HoverItem {
+    // like minecraft:diamond_sword
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<i32>,
+    // the item's nbt, as snbt
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HoverEntity {
+    // like minecraft:pig
+    #[serde(rename = "type")]
+    pub kind: String,
+    // hyphenated
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<Box<TextComponent>>,
+}
+
+// anything that's None is inherited from the parent component
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Style {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<Color>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bold: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub italic: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub underlined: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strikethrough: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub obfuscated: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub font: Option<String>,
+    // put in the chat box when the text is shift clicked
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub insertion: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub click_event: Option<Box<ClickEvent>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hover_event: Option<Box<HoverEvent>>,
+}
+
+impl Style {
+    // this style with the gaps filled in from parent's
+    pub fn inherit(&self, parent: &Style) -> Style {
+        Style {
+            color: self.color.or(parent.color),
+            bold: self.bold.or(parent.bold),
+            italic: self.italic.or(parent.italic),
+            underlined: self.underlined.or(parent.underlined),
+            strikethrough: self.strikethrough.or(parent.strikethrough),
+            obfuscated: self.obfuscated.or(parent.obfuscated),
+            font: self.font.clone().or_else(|| parent.font.clone()),
+            insertion: self.insertion.clone().or_else(|| parent.insertion.clone()),
+            click_event: self.click_event.clone().or_else(|| parent.click_event.clone()),
+            hover_event: self.hover_event.clone().or_else(|| parent.hover_event.clone()),
+        }
+    }
+
+    // the § codes for the parts of the style legacy text can show, color first
+    fn legacy_codes(&self) -> String {
+        let mut codes = String::new();
+        if let Some(color) = self.color {
+            codes.push('§');
+            codes.push(color.code());
+        }
+        let flags = [
+            (self.obfuscated, 'k'),
+            (self.bold, 'l'),
+            (self.strikethrough, 'm'),
+            (self.underlined, 'n'),
+            (self.italic, 'o'),
+        ];
+        for (flag, code) in flags.iter() {
+            if *flag == Some(true) {
+                codes.push('§');
+                codes.push(*code);
+            }
+        }
+        codes
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Score {
+    // a player name or a selector
+    pub name: String,
+    pub objective: String,
+    // the server fills this in before sending it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+}
+
+// what the component shows, before its children
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
+pub enum Content {
+    Text {
+        text: String,
+    },
+    // a key from the client's language file, with %s in it filled in by with
+    Translate {
+        translate: String,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        with: Vec<TextComponent>,
+    },
+    Score {
+        score: Box<Score>,
+    },
+    // the names of the entities the selector picks, the server resolves it before sending
+    Selector {
+        selector: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        separator: Option<Box<TextComponent>>,
+    },
+    // the key bound to something, like key.jump
+    Keybind {
+        keybind: String,
+    },
+}
+
+// the rarely used parts are boxed, since this goes in packets
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct TextComponent {
+    #[serde(flatten)]
+    pub content: Content,
+    #[serde(flatten)]
+    pub style: Style,
+    // shown after content, with this component's style as their parent
+    #[serde(rename = "extra", skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<TextComponent>,
+}
+
+macro_rules! style_setters {
+    ($($name: ident),*) => {$(
+        pub fn $name(mut self, $name: bool) -> Self {
+            self.style.$name = Some($name);
+            self
+        }
+    )*};
+}
+
+impl TextComponent {
+    pub fn new(content: Content) -> Self {
+        Self { content, style: Style::default(), children: Vec::new() }
+    }
+
+    pub fn text(text: impl Into<String>) -> Self {
+        Self::new(Content::Text { text: text.into() })
+    }
+
+    pub fn translate(key: impl Into<String>, args: Vec<TextComponent>) -> Self {
+        Self::new(Content::Translate { translate: key.into(), with: args })
+    }
+
+    pub fn score(name: impl Into<String>, objective: impl Into<String>) -> Self {
+        Self::new(Content::Score { score: Box::new(Score { name: name.into(), objective: objective.into(), value: None }) })
+    }
+
+    pub fn selector(selector: impl Into<String>) -> Self {
+        Self::new(Content::Selector { selector: selector.into(), separator: None })
+    }
+
+    pub fn keybind(key: impl Into<String>) -> Self {
+        Self::new(Content::Keybind { keybind: key.into() })
+    }
+
+    pub fn color(mut self, color: Color) -> Self {
+        self.style.color = Some(color);
+        self
+    }
+
+    style_setters!(bold, italic, underlined, strikethrough, obfuscated);
+
+    pub fn font(mut self, font: impl Into<String>) -> Self {
+        self.style.font = Some(font.into());
+        self
+    }
+
+    pub fn insertion(mut self, insertion: impl Into<String>) -> Self {
+        self.style.insertion = Some(insertion.into());
+        self
+    }
+
+    pub fn on_click(mut self, event: ClickEvent) -> Self {
+        self.style.click_event = Some(Box::new(event));
+        self
+    }
+
+    pub fn on_hover(mut self, event: HoverEvent) -> Self {
+        self.style.hover_event = Some(Box::new(event));
+        self
+    }
+
+    pub fn style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    pub fn child(mut self, child: impl Into<TextComponent>) -> Self {
+        self.children.push(child.into());
+        self
+    }
+
+    pub fn to_json(&self) -> String {
+        // can't fail, everything in here serializes fine
+        serde_json::to_string(self).unwrap()
+    }
+
+    // the text of just this component, without its children
+    // there's no language file here, so translations are their key with the args filled in
+    fn content_text(&self) -> Cow<'_, str> {
+        match &self.content {
+            Content::Text { text } => Cow::Borrowed(text),
+            Content::Translate { translate, with } => Cow::Owned(translate_plain(translate, with)),
+            Content::Score { score } => Cow::Borrowed(score.value.as_deref().unwrap_or("")),
+            Content::Selector { selector, .. } => Cow::Borrowed(selector),
+            Content::Keybind { keybind } => Cow::Borrowed(keybind),
+        }
+    }
+
+    fn write_plain(&self, out: &mut String) {
+        out.push_str(&self.content_text());
+        for child in &self.children {
+            child.write_plain(out);
+        }
+    }
+
+    // all the text without any styling
+    pub fn to_plain(&self) -> String {
+        let mut out = String::new();
+        self.write_plain(&mut out);
+        out
+    }
+
+    // text with § codes, like old clients and the legacy ping use
+    // a color code turns off bold and the rest, and §r turns off everything
+    pub fn from_legacy(text: &str) -> Self {
+        let mut segments = Vec::new();
+        let mut style = Style::default();
+        let mut current = String::new();
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            if c != '§' {
+                current.push(c);
+                continue;
+            }
+            let mut next = style.clone();
+            match chars.next().map(|code| code.to_ascii_lowercase()) {
+                Some('k') => next.obfuscated = Some(true),
+                Some('l') => next.bold = Some(true),
+                Some('m') => next.strikethrough = Some(true),
+                Some('n') => next.underlined = Some(true),
+                Some('o') => next.italic = Some(true),
+                Some('r') => next = Style::default(),
+                Some(code) => match Color::from_code(code) {
+                    Some(color) => next = Style { color: Some(color), ..Style::default() },
+                    // unknown codes get dropped, like the client does
+                    None => continue,
+                },
+                // a § at the very end is just text
+                None => current.push(c),
+            }
+            if next != style {
+                if !current.is_empty() {
+                    segments.push(Self::text(std::mem::take(&mut current)).style(style));
+                }
+                style = next;
+            }
+        }
+        if !current.is_empty() || segments.is_empty() {
+            segments.push(Self::text(current).style(style));
+        }
+
+        if segments.len() == 1 {
+            segments.pop().unwrap()
+        } else {
+            Self { children: segments, ..Self::text("") }
+        }
+    }
+
+    fn write_legacy(&self, parent: &Style, written: &mut String, out: &mut String) {
+        let style = self.style.inherit(parent);
+        let text = self.content_text();
+        if !text.is_empty() {
+            let codes = style.legacy_codes();
+            if codes != *written {
+                // colors reset the formatting anyway, otherwise it takes a §r
+                if style.color.is_none() && !written.is_empty() {
+                    out.push_str("§r");
+                }
+                out.push_str(&codes);
+                *written = codes;
+            }
+            out.push_str(&text);
+        }
+        for child in &self.children {
+            child.write_legacy(&style, written, out);
+        }
+    }
+
+    // rgb colors become the closest named color, and everything legacy text can't show is dropped
+    pub fn to_legacy(&self) -> String {
+        let mut out = String::new();
+        self.write_legacy(&Style::default(), &mut String::new(), &mut out);
+        out
+    }
+}
+
+// fills in %s and %1$s with the args, %% is a %
+fn translate_plain(format: &str, args: &[TextComponent]) -> String {
+    let mut out = String::new();
+    let mut next_arg = 0;
+    let mut rest = format;
+    while let Some(start) = rest.find('%') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start + 1..];
+        let (index, len) = if rest.starts_with('%') {
+            out.push('%');
+            rest = &rest[1..];
+            continue;
+        } else if rest.starts_with('s') {
+            next_arg += 1;
+            (next_arg - 1, 1)
+        } else {
+            let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+            match (rest[..digits].parse::<usize>(), rest[digits..].starts_with("$s")) {
+                (Ok(position), true) if position > 0 => (position - 1, digits + 2),
+                _ => {
+                    out.push('%');
+                    continue;
+                },
+            }
+        };
+        if let Some(arg) = args.get(index) {
+            arg.write_plain(&mut out);
+        }
+        rest = &rest[len..];
+    }
+    out.push_str(rest);
+    out
+}
+
+impl Default for TextComponent {
+    fn default() -> Self {
+        Self::text("")
+    }
+}
+
+impl From<&str> for TextComponent {
+    fn from(text: &str) -> Self {
+        Self::text(text)
+    }
+}
+
+impl From<String> for TextComponent {
+    fn from(text: String) -> Self {
+        Self::text(text)
+    }
+}
+
+// the plain text
+impl Display for TextComponent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_plain())
+    }
+}
+
+// everything an object component can have, the content gets picked out of it
+#[derive(Deserialize)]
+struct RawComponent {
+    text: Option<String>,
+    translate: Option<String>,
+    #[serde(default)]
+    with: Vec<TextComponent>,
+    score: Option<Box<Score>>,
+    selector: Option<String>,
+    separator: Option<Box<TextComponent>>,
+    keybind: Option<String>,
+    #[serde(flatten)]
+    style: Style,
+    #[serde(default)]
+    extra: Vec<TextComponent>,
+}
+
+impl RawComponent {
+    // when there's more than one kind of content, the one vanilla checks first wins
+    fn into_component(self) -> Option<TextComponent> {
+        let content = if let Some(text) = self.text {
+            Content::Text { text }
+        } else if let Some(translate) = self.translate {
+            Content::Translate { translate, with: self.with }
+        } else if let Some(score) = self.score {
+            Content::Score { score }
+        } else if let Some(selector) = self.selector {
+            Content::Selector { selector, separator: self.separator }
+        } else {
+            Content::Keybind { keybind: self.keybind? }
+        };
+        Some(TextComponent { content, style: self.style, children: self.extra })
+    }
+}
+
+// a component can also be a string, which is just text, or an array, where
+// the first element is the parent of the rest
+impl<'de> Deserialize<'de> for TextComponent {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ComponentVisitor)
+    }
+}
+
+struct ComponentVisitor;
+
+impl<'de> serde::de::Visitor<'de> for ComponentVisitor {
+    type Value = TextComponent;
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "a chat component")
+    }
+
+    fn visit_str<E: serde::de::Error>(self, text: &str) -> Result<TextComponent, E> {
+        Ok(TextComponent::text(text))
+    }
+
+    // numbers and bools are text too
+    fn visit_bool<E: serde::de::Error>(self, value: bool) -> Result<TextComponent, E> {
+        Ok(TextComponent::text(value.to_string()))
+    }
+
+    fn visit_i64<E: serde::de::Error>(self, value: i64) -> Result<TextComponent, E> {
+        Ok(TextComponent::text(value.to_string()))
+    }
+
+    fn visit_u64<E: serde::de::Error>(self, value: u64) -> Result<TextComponent, E> {
+        Ok(TextComponent::text(value.to_string()))
+    }
+
+    fn visit_f64<E: serde::de::Error>(self, value: f64) -> Result<TextComponent, E> {
+        Ok(TextComponent::text(value.to_string()))
+    }
+
+    fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<TextComponent, A::Error> {
+        let mut parent: TextComponent = seq.next_element()?
+            .ok_or_else(|| serde::de::Error::invalid_length(0, &"at least one component"))?;
+        while let Some(child) = seq.next_element()? {
+            parent.children.push(child);
+        }
+        Ok(parent)
+    }
+
+    fn visit_map<A: serde::de::MapAccess<'de>>(self, map: A) -> Result<TextComponent, A::Error> {
+        let raw = RawComponent::deserialize(serde::de::value::MapAccessDeserializer::new(map))?;
+        raw.into_component()
+            .ok_or_else(|| serde::de::Error::custom("a chat component needs text, translate, score, selector or keybind"))
+    }
+}
+
+impl de::Deserialize<'_> for TextComponent {
+    type Value = Self;
+
+    fn deserialize(input: &mut ByteReader<'_>) -> de::Result<Self> {
+        let offset = input.offset();
+        let json = de::read_str(input, MAX_CHAT_LENGTH)?;
+        serde_json::from_str(json).map_err(|err| de::Error::new(ErrorKind::BadJson, offset).with_detail(err))
+    }
+}
+
+impl ser::Serialize for TextComponent {
+    type Value = Self;
+
+    fn serialize(value: &Self, output: &mut ByteWriter<'_>) {
+        <str as ser::Serialize>::serialize(&value.to_json(), output);
+    }
+}
@@ -3,21 +3,41 @@ use std::pin::Pin;
 use std::future::Future;
 use tokio::net::TcpStream;
 use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
-use tokio::io::{self, AsyncRead, AsyncReadExt, AsyncWriteExt, AsyncBufRead, AsyncBufReadExt, BufReader, ReadBuf};
+use tokio::io::{self, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, AsyncBufRead, AsyncBufReadExt, BufReader, BufWriter, ReadBuf};
 use flate2;
 use crate::varnum::VarInt;
+use crate::encryption::{Encryptor, Decryptor};
+use crate::legacy::{self, LegacyPing};
+use crate::de::{self, Deserialize};
+use crate::ser::{Serialize, ByteWriter};
+use crate::packets::Packet;
 
 struct ConnReaderInner {
     // would make a type alias for this, but cant think of a good name
     // ReadReader?
     read: OwnedReadHalf,
-    // encryption too, later
+    decrypt: Option<Decryptor>,
+    // bytes that were already buffered when encryption was turned on
+    // they're decrypted already, and get read before anything else
+    pending: Vec<u8>,
 }
 
 impl AsyncRead for ConnReaderInner {
     fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
-        let read = Pin::new(&mut self.read);
-        read.poll_read(cx, buf)
+        let self_ = &mut *self;
+        if !self_.pending.is_empty() {
+            let len = std::cmp::min(buf.remaining(), self_.pending.len());
+            buf.put_slice(&self_.pending[..len]);
+            self_.pending.drain(..len);
+            return Poll::Ready(Ok(()));
+        }
+
+        let old_filled = buf.filled().len();
+        let res = Pin::new(&mut self_.read).poll_read(cx, buf);
+        if let Some(decrypt) = &mut self_.decrypt {
+            decrypt.decrypt(&mut buf.filled_mut()[old_filled..]);
+        }
+        res
     }
 }
 
@@ -46,10 +66,7 @@ async fn async_decompress<R: AsyncBufRead + Unpin>(read: &mut R, decompress: &mu
         let produced = (decompress.total_out() - old_total_out) as usize;
         read.consume(consumed);
 
-        let is_stream_end = match &decompress_result {
-            Ok(flate2::Status::StreamEnd) => true,
-            _ => false
-        };
+        let is_stream_end = matches!(decompress_result, Ok(flate2::Status::StreamEnd));
         if produced == 0 && !is_stream_end {
             continue;
         }
@@ -72,11 +89,66 @@ pub struct PacketReader<'a> {
 }
 
 impl<'a> PacketReader<'a> {
-    pub async fn read_all(mut self) -> io::Result<Vec<u8>> {
-        let mut res = vec![0; self.length];
-        self.read_exact(&mut res).await?;
+    pub async fn read_all(self) -> io::Result<Vec<u8>> {
+        let mut res = Vec::new();
+        self.read_into(&mut res).await?;
         Ok(res)
     }
+
+    // replaces what's in buf with the packet, reusing its allocation
+    pub async fn read_into(mut self, buf: &mut Vec<u8>) -> io::Result<()> {
+        buf.clear();
+        buf.resize(self.length, 0);
+        match self.read_exact(buf).await {
+            // the zlib stream ended before the data length, or the frame did
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof && self.decompress.is_some() && self.frame_done() => {
+                return Err(invalid_data("compressed packet is shorter than its data length"));
+            },
+            res => res?,
+        };
+        self.finish().await
+    }
+
+    fn frame_done(&mut self) -> bool {
+        self.read.limit() == 0 || self.decompress.as_mut().is_some_and(|decompress| stream_ended(decompress))
+    }
+
+    // the zlib stream has to end right where the data length says, and the frame right after it,
+    // otherwise the checksum or anything after it would get read as the next frame
+    async fn finish(&mut self) -> io::Result<()> {
+        let decompress = match &mut self.decompress {
+            Some(decompress) => decompress,
+            None => return Ok(()),
+        };
+        loop {
+            let compressed = self.read.fill_buf().await?;
+            let flush = if compressed.is_empty() { flate2::FlushDecompress::Finish } else { flate2::FlushDecompress::None };
+            let (old_total_in, old_total_out) = (decompress.total_in(), decompress.total_out());
+            let status = decompress.decompress(compressed, &mut [0; 1], flush);
+            let consumed = (decompress.total_in() - old_total_in) as usize;
+            let produced = decompress.total_out() - old_total_out;
+            let empty = compressed.is_empty();
+            self.read.consume(consumed);
+            if produced > 0 {
+                return Err(invalid_data("compressed packet is longer than its data length"));
+            }
+            match status {
+                Ok(flate2::Status::StreamEnd) => break,
+                Err(_) => return Err(invalid_data("corrupt deflate stream")),
+                _ if empty && self.read.limit() == 0 => return Err(invalid_data("compressed packet ends before its zlib stream does")),
+                _ if empty => return Err(io::ErrorKind::UnexpectedEof.into()),
+                _ => {},
+            }
+        }
+        if self.read.limit() != 0 {
+            return Err(invalid_data("compressed packet has data after its zlib stream"));
+        }
+        Ok(())
+    }
+}
+
+fn stream_ended(decompress: &mut flate2::Decompress) -> bool {
+    matches!(decompress.decompress(&[], &mut [0; 1], flate2::FlushDecompress::Finish), Ok(flate2::Status::StreamEnd))
 }
 
 impl AsyncRead for PacketReader<'_> {
@@ -97,7 +169,7 @@ impl AsyncRead for PacketReader<'_> {
                 // if it changes later, it might not be anymore
                 std::mem::drop(decompressing_future);
 
-                return Poll::Ready(match res {
+                Poll::Ready(match res {
                     Ok(len_read) => {
                         buf.advance(len_read);
                         Ok(())
@@ -106,7 +178,7 @@ impl AsyncRead for PacketReader<'_> {
                 })
             },
             None => {
-                return Pin::new(&mut self_.read).poll_read(cx, buf);
+                Pin::new(&mut self_.read).poll_read(cx, buf)
             }
         }
     }
@@ -114,28 +186,127 @@ impl AsyncRead for PacketReader<'_> {
 
 pub enum PacketOrLegacyPing<'a> {
     Packet(PacketReader<'a>),
-    // this will later have a reader in it or something
-    LegacyPing
+    LegacyPing(LegacyPing)
+}
+
+// the largest packet length that fits in a 3 byte VarInt, vanilla won't read more
+pub const MAX_FRAME_LENGTH: usize = (1 << 21) - 1;
+// the largest data length a compressed packet is allowed to declare
+pub const MAX_DATA_LENGTH: usize = 1 << 23;
+
+// how big packets from the client can be, checked before anything gets allocated for them
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Limits {
+    // the length in the frame, so the compressed size if it's compressed
+    pub max_frame_length: usize,
+    // the size once it's decompressed
+    pub max_data_length: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_frame_length: MAX_FRAME_LENGTH,
+            max_data_length: MAX_DATA_LENGTH,
+        }
+    }
+}
+
+// frames that get rejected, these are the inner error of an InvalidData io::Error
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameError {
+    NegativeLength,
+    TooLong { len: usize, max: usize },
+    DataTooLong { len: usize, max: usize },
+}
+
+impl std::fmt::Display for FrameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NegativeLength => write!(f, "negative packet length"),
+            Self::TooLong { len, max } => write!(f, "packet length {} is over the maximum of {}", len, max),
+            Self::DataTooLong { len, max } => write!(f, "data length {} is over the maximum of {}", len, max),
+        }
+    }
+}
+
+impl std::error::Error for FrameError {}
+
+impl From<FrameError> for io::Error {
+    fn from(err: FrameError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+pub(crate) fn invalid_data(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
 }
 
 struct RecvCompression {
-    decompress: flate2::Decompress
+    decompress: flate2::Decompress,
+    threshold: usize,
 }
 
 pub struct Recv {
     read: ConnReader,
     compression: Option<RecvCompression>,
+    limits: Limits,
+    // reused between packets by read_packet_data, so reading them doesn't allocate every time
+    buf: Vec<u8>,
 }
 
 impl Recv {
     pub fn new(read: OwnedReadHalf) -> Self {
         Self {
-            read: BufReader::new(ConnReaderInner { read } ),
+            read: BufReader::new(ConnReaderInner { read, decrypt: None, pending: Vec::new() }),
             compression: None,
+            limits: Limits::default(),
+            buf: Vec::new(),
+        }
+    }
+
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    pub fn limits(&self) -> Limits {
+        self.limits
+    }
+
+    fn check_frame_length(&self, packet_length: i32) -> io::Result<usize> {
+        use std::convert::TryFrom;
+
+        let len = usize::try_from(packet_length).map_err(|_| FrameError::NegativeLength)?;
+        let max = self.limits.max_frame_length;
+        if len > max {
+            return Err(FrameError::TooLong { len, max }.into());
         }
+        Ok(len)
     }
 
-    pub async fn read_packet_with_length(&mut self, mut packet_length: usize) -> io::Result<PacketReader<'_>> {
+    pub fn enable_encryption(&mut self, shared_secret: &[u8; 16]) {
+        let mut decrypt = Decryptor::new(shared_secret, shared_secret);
+        // anything left in the BufReader arrived encrypted, so pull it out and decrypt it
+        let mut buffered = self.read.buffer().to_vec();
+        decrypt.decrypt(&mut buffered);
+        Pin::new(&mut self.read).consume(buffered.len());
+
+        let inner = self.read.get_mut();
+        inner.pending.extend_from_slice(&buffered);
+        inner.decrypt = Some(decrypt);
+    }
+
+    // packets with a data length below threshold must be sent uncompressed
+    pub fn enable_compression(&mut self, threshold: usize) {
+        self.compression = Some(RecvCompression {
+            decompress: flate2::Decompress::new(true),
+            threshold,
+        });
+    }
+
+    pub async fn read_packet_with_length(&mut self, packet_length: i32) -> io::Result<PacketReader<'_>> {
+        let mut packet_length = self.check_frame_length(packet_length)?;
+        let max_data_length = self.limits.max_data_length;
         let mut data_length = packet_length;
         let decompress = match &mut self.compression {
             None => None,
@@ -143,15 +314,30 @@ impl Recv {
                 // if compression is enabled, read the data length
                 // and use compression if it's non-zero
                 let data_length_info = VarInt::new().read_from_async_get_state(&mut self.read).await?;
-                packet_length -= data_length_info.length as usize;
-                data_length = data_length_info.val as usize;
-    
-                if data_length != 0 {
-                    let decompress = &mut compression.decompress;
-                    decompress.reset(true); // true means expect a zlib header, which will appear
-                    Some(decompress)
-                } else {
-                    None
+                packet_length = packet_length.checked_sub(data_length_info.length)
+                    .ok_or_else(|| invalid_data("packet length too short for data length"))?;
+
+                if data_length_info.val < 0 {
+                    return Err(invalid_data("negative data length"));
+                }
+                match data_length_info.val as usize {
+                    // the rest of the packet is uncompressed
+                    0 => {
+                        data_length = packet_length;
+                        None
+                    },
+                    len if len < compression.threshold => {
+                        return Err(invalid_data("compressed packet is below the compression threshold"));
+                    },
+                    len if len > max_data_length => {
+                        return Err(FrameError::DataTooLong { len, max: max_data_length }.into());
+                    },
+                    len => {
+                        data_length = len;
+                        let decompress = &mut compression.decompress;
+                        decompress.reset(true); // true means expect a zlib header, which will appear
+                        Some(decompress)
+                    }
                 }
             }
         };
@@ -165,38 +351,261 @@ impl Recv {
     }
 
     pub async fn read_packet(&mut self) -> io::Result<PacketReader<'_>> {
-        let packet_length = VarInt::new().read_from_async(&mut self.read).await? as usize;
+        let packet_length = VarInt::new().read_from_async(&mut self.read).await?;
         self.read_packet_with_length(packet_length).await
     }
 
+    // reads a whole packet into the receive buffer, it's only valid until the next read
+    pub async fn read_packet_data(&mut self) -> io::Result<&[u8]> {
+        // taken out while reading since the PacketReader borrows all of self
+        let mut buf = std::mem::take(&mut self.buf);
+        let res = match self.read_packet().await {
+            Ok(packet) => packet.read_into(&mut buf).await,
+            Err(err) => Err(err),
+        };
+        self.buf = buf;
+        res?;
+        Ok(&self.buf)
+    }
+
+    // reads and deserializes a whole packet at once, it has to use up all of it
+    pub async fn recv_packet<P: for<'de> Deserialize<'de, Value = P>>(&mut self) -> io::Result<P> {
+        let data = self.read_packet_data().await?;
+        Ok(de::deserialize_all::<P>(data)?)
+    }
+
+    // reads and throws away whatever the client sends until it closes its side
+    pub async fn drain(&mut self) -> io::Result<()> {
+        let mut buf = [0; 1024];
+        while self.read.read(&mut buf).await? != 0 {}
+        Ok(())
+    }
+
     // old clients may send an initial packet following a different format, so be able to handle those also
     pub async fn read_packet_or_legacy_ping(&mut self) -> io::Result<PacketOrLegacyPing<'_>> {
         let mut packet_length_reader = VarInt::new();
         let first_byte = self.read.read_u8().await?;
         if first_byte == 0xfe {
-            return Ok(PacketOrLegacyPing::LegacyPing);
+            return Ok(PacketOrLegacyPing::LegacyPing(legacy::read_legacy_ping(&mut self.read).await?));
         }
         packet_length_reader = packet_length_reader.try_read_byte(first_byte);
-        let packet_length = packet_length_reader.read_from_async(&mut self.read).await? as usize;
+        let packet_length = packet_length_reader.read_from_async(&mut self.read).await?;
         Ok(PacketOrLegacyPing::Packet(self.read_packet_with_length(packet_length).await?))
     }
 }
 
+struct ConnWriterInner {
+    write: OwnedWriteHalf,
+    encrypt: Option<Encryptor>,
+    // encrypted bytes that the socket hasn't taken yet
+    // these have already gone through the cipher, so they can't be dropped or written again
+    encrypted: Vec<u8>,
+    encrypted_written: usize,
+}
+
+impl ConnWriterInner {
+    fn poll_write_encrypted(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.encrypted_written < self.encrypted.len() {
+            let to_write = &self.encrypted[self.encrypted_written..];
+            match Pin::new(&mut self.write).poll_write(cx, to_write) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Ready(Ok(0)) => return Poll::Ready(Err(io::ErrorKind::WriteZero.into())),
+                Poll::Ready(Ok(len)) => self.encrypted_written += len,
+            }
+        }
+        self.encrypted.clear();
+        self.encrypted_written = 0;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for ConnWriterInner {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let self_ = &mut *self;
+        if self_.encrypt.is_none() {
+            return Pin::new(&mut self_.write).poll_write(cx, buf);
+        }
+
+        // only take more once everything encrypted so far is written
+        match self_.poll_write_encrypted(cx) {
+            Poll::Ready(Ok(())) => {},
+            other => return other.map(|res| res.map(|_| 0)),
+        }
+        self_.encrypted.extend_from_slice(buf);
+        self_.encrypt.as_mut().unwrap().encrypt(&mut self_.encrypted);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.poll_write_encrypted(cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut self.write).poll_flush(cx),
+            other => other,
+        }
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.poll_write_encrypted(cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut self.write).poll_shutdown(cx),
+            other => other,
+        }
+    }
+}
+
+type ConnWriter = BufWriter<ConnWriterInner>;
+
+struct SendCompression {
+    compress: flate2::Compress,
+    threshold: usize,
+    // reused between packets so compressing doesn't allocate every time
+    buf: Vec<u8>,
+}
+
+fn compress_into(compress: &mut flate2::Compress, data: &[u8], dst: &mut Vec<u8>) -> io::Result<()> {
+    compress.reset();
+    dst.clear();
+    loop {
+        // compress_vec only writes into spare capacity, so make sure there is some
+        if dst.len() == dst.capacity() {
+            dst.reserve(std::cmp::max(64, data.len() / 2));
+        }
+        let consumed = compress.total_in() as usize;
+        let status = compress.compress_vec(&data[consumed..], dst, flate2::FlushCompress::Finish)
+            .map_err(|_| io::Error::other("failed to compress packet"))?;
+        if status == flate2::Status::StreamEnd {
+            return Ok(());
+        }
+    }
+}
+
+// big enough to hold a batch of chunk packets before hitting the socket
+const SEND_BUFFER_SIZE: usize = 1 << 16;
+
 pub struct Send {
-    write: OwnedWriteHalf
+    write: ConnWriter,
+    compression: Option<SendCompression>,
+    // packets get serialized into here before they're framed
+    buf: Vec<u8>,
 }
 
 impl Send {
     pub fn new(write: OwnedWriteHalf) -> Self {
-        Self { write }
+        Self {
+            write: BufWriter::with_capacity(SEND_BUFFER_SIZE, ConnWriterInner {
+                write,
+                encrypt: None,
+                encrypted: Vec::new(),
+                encrypted_written: 0,
+            }),
+            compression: None,
+            buf: Vec::new(),
+        }
+    }
+
+    // packets with at least threshold bytes of data will be compressed
+    pub fn enable_compression(&mut self, threshold: usize) {
+        self.compression = Some(SendCompression {
+            compress: flate2::Compress::new(flate2::Compression::default(), true),
+            threshold,
+            buf: Vec::new(),
+        });
+    }
+
+    // anything written before this is sent unencrypted
+    pub async fn enable_encryption(&mut self, shared_secret: &[u8; 16]) -> io::Result<()> {
+        self.write.flush().await?;
+        self.write.get_mut().encrypt = Some(Encryptor::new(shared_secret, shared_secret));
+        Ok(())
+    }
+
+    async fn write_frame(&mut self) -> io::Result<()> {
+        let data = &self.buf;
+        // the header holds the packet length, and the data length if compression is on
+        let mut header = Vec::with_capacity(10);
+        match &mut self.compression {
+            None => {
+                VarInt::write_to(data.len() as i32, &mut header);
+                self.write.write_all(&header).await?;
+                self.write.write_all(data).await?;
+            },
+            Some(compression) if data.len() < compression.threshold => {
+                // a data length of 0 means the packet isn't compressed
+                VarInt::write_to(data.len() as i32 + 1, &mut header);
+                header.push(0);
+                self.write.write_all(&header).await?;
+                self.write.write_all(data).await?;
+            },
+            Some(compression) => {
+                compress_into(&mut compression.compress, data, &mut compression.buf)?;
+                let mut data_length = Vec::with_capacity(5);
+                VarInt::write_to(data.len() as i32, &mut data_length);
+                VarInt::write_to((data_length.len() + compression.buf.len()) as i32, &mut header);
+                header.extend_from_slice(&data_length);
+                self.write.write_all(&header).await?;
+                self.write.write_all(&compression.buf).await?;
+            }
+        }
+        Ok(())
     }
 
-    pub fn shutdown(self) {
-        // drop self.write
+    // writes the packet into the send buffer, but doesn't flush it
+    // lots of packets can be batched this way, then sent with flush
+    pub async fn write_packet<P: Packet>(&mut self, packet: &P) -> io::Result<()> {
+        self.buf.clear();
+        let mut output = ByteWriter::new(&mut self.buf);
+        VarInt::serialize(&P::ID, &mut output);
+        P::serialize(packet, &mut output);
+        self.write_frame().await
     }
+
+    // for legacy packets, which don't get framed
+    pub async fn send_unframed(&mut self, data: &[u8]) -> io::Result<()> {
+        self.write.write_all(data).await?;
+        self.flush().await
+    }
+
+    // for packets that are already serialized, id included
+    pub async fn write_packet_bytes(&mut self, data: &[u8]) -> io::Result<()> {
+        self.buf.clear();
+        self.buf.extend_from_slice(data);
+        self.write_frame().await
+    }
+
+    // for types that write their own packet id, like the packet enums
+    pub async fn write_packet_data<T: Serialize<Value = T>>(&mut self, packet: &T) -> io::Result<()> {
+        self.buf.clear();
+        T::serialize(packet, &mut ByteWriter::new(&mut self.buf));
+        self.write_frame().await
+    }
+
+    pub async fn flush(&mut self) -> io::Result<()> {
+        self.write.flush().await
+    }
+
+    pub async fn send_packet<P: Packet>(&mut self, packet: &P) -> io::Result<()> {
+        self.write_packet(packet).await?;
+        self.flush().await
+    }
+
+    pub async fn shutdown(mut self) -> io::Result<()> {
+        // flushes anything that's still buffered first
+        self.write.shutdown().await
+    }
+}
+
+// call right after sending Set Compression, so both sides switch at the same packet
+pub fn enable_compression(send: &mut Send, recv: &mut Recv, threshold: usize) {
+    send.enable_compression(threshold);
+    recv.enable_compression(threshold);
+}
+
+// call right after receiving Encryption Response, everything after it is encrypted
+pub async fn enable_encryption(send: &mut Send, recv: &mut Recv, shared_secret: &[u8; 16]) -> io::Result<()> {
+    send.enable_encryption(shared_secret).await?;
+    recv.enable_encryption(shared_secret);
+    Ok(())
 }
 
-// also will return a Send, eventually
 pub fn make_conn(conn: TcpStream) -> (Send, Recv) {
     let (read, write) = conn.into_split();
     (Send::new(write), Recv::new(read))
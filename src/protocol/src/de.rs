@@ -1,43 +1,218 @@
-use num;
 use crate::varnum::{VarNumReader, VarInt};
 
-#[derive(Debug)]
-pub enum Error {
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
     Eof,
-    BadPacketId,
+    BadPacketId(i32),
     BadEnumTag,
     BadVarNum,
     BadUtf8,
+    BadLength,
+    BadBool,
+    // a string or length prefixed field with more than the limit allows
+    TooLong { len: usize, max: usize },
+    // bytes left over after the packet, how many
+    TrailingBytes(usize),
+    // nbt nested deeper than nbt::MAX_DEPTH
+    TooDeep,
+    // json or nbt that's well formed but doesn't fit the serde type it's read into
+    BadJson,
+    BadNbt,
+    // a packet the client's version doesn't have, from translating to it
+    NotInVersion,
+}
+
+impl std::fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Eof => write!(f, "unexpected end of packet"),
+            Self::BadPacketId(id) => write!(f, "unknown packet id {:#04x}", id),
+            Self::BadEnumTag => write!(f, "unknown enum tag"),
+            Self::BadVarNum => write!(f, "varnum too long"),
+            Self::BadUtf8 => write!(f, "invalid utf-8"),
+            Self::BadLength => write!(f, "invalid length"),
+            Self::BadBool => write!(f, "bool that isn't 0 or 1"),
+            Self::TooLong { len, max } => write!(f, "length {} is over the maximum of {}", len, max),
+            Self::TrailingBytes(len) => write!(f, "{} bytes left over", len),
+            Self::TooDeep => write!(f, "nested too deep"),
+            Self::BadJson => write!(f, "json doesn't match its type"),
+            Self::BadNbt => write!(f, "nbt doesn't match its type"),
+            Self::NotInVersion => write!(f, "packet doesn't exist in this version"),
+        }
+    }
+}
+
+// one step of the path to the field that failed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PathSegment {
+    Field(&'static str),
+    Index(usize),
+}
+
+// what went wrong and where, the path and packet get added on the way out
+// by the derived impls (in_field) and the packet enums (in_packet)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Error {
+    kind: ErrorKind,
+    // from the start of the packet, including the id
+    offset: usize,
+    // innermost last, built up backwards
+    path: Vec<PathSegment>,
+    packet: Option<(&'static str, i32)>,
+    // more about what went wrong, like the message from serde
+    detail: Option<String>,
+}
+
+impl Error {
+    pub fn new(kind: ErrorKind, offset: usize) -> Self {
+        Self { kind, offset, path: Vec::new(), packet: None, detail: None }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn path(&self) -> &[PathSegment] {
+        &self.path
+    }
+
+    // name and id of the packet that failed, if it's known
+    pub fn packet(&self) -> Option<(&'static str, i32)> {
+        self.packet
+    }
+
+    pub fn detail(&self) -> Option<&str> {
+        self.detail.as_deref()
+    }
+
+    pub fn with_detail(mut self, detail: impl std::fmt::Display) -> Self {
+        self.detail = Some(detail.to_string());
+        self
+    }
+
+    pub fn in_field(mut self, name: &'static str) -> Self {
+        self.path.insert(0, PathSegment::Field(name));
+        self
+    }
+
+    pub fn in_index(mut self, index: usize) -> Self {
+        self.path.insert(0, PathSegment::Index(index));
+        self
+    }
+
+    // the first packet set sticks, so nesting doesn't overwrite it
+    pub fn in_packet(mut self, name: &'static str, id: i32) -> Self {
+        self.packet.get_or_insert((name, id));
+        self
+    }
+}
+
+// e.g. "varnum too long at Handshake.next_state (packet 0x00, byte 5)"
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if self.packet.is_some() || !self.path.is_empty() {
+            write!(f, " at ")?;
+        }
+        let mut first = true;
+        if let Some((name, _)) = self.packet {
+            write!(f, "{}", name)?;
+            first = false;
+        }
+        for segment in &self.path {
+            match segment {
+                PathSegment::Field(name) if first => write!(f, "{}", name)?,
+                PathSegment::Field(name) => write!(f, ".{}", name)?,
+                PathSegment::Index(index) => write!(f, "[{}]", index)?,
+            }
+            first = false;
+        }
+        match self.packet {
+            Some((_, id)) => write!(f, " (packet {:#04x}, byte {})", id, self.offset)?,
+            None => write!(f, " (byte {})", self.offset)?,
+        }
+        match &self.detail {
+            Some(detail) => write!(f, ": {}", detail),
+            None => Ok(()),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<Error> for std::io::Error {
+    fn from(err: Error) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, err)
+    }
 }
 
 pub type Result<V> = std::result::Result<V, Error>;
 
+// runs read, adding name to the path of the error if it fails
+// the derived impls wrap every field in this
+pub fn field<V>(name: &'static str, read: impl FnOnce() -> Result<V>) -> Result<V> {
+    read().map_err(|err| err.in_field(name))
+}
+
+// reads a whole packet, it's an error if anything is left over
+pub fn deserialize_all<'de, T: Deserialize<'de>>(bytes: &'de [u8]) -> Result<T::Value> {
+    let mut input = ByteReader::new(bytes);
+    let value = T::deserialize(&mut input)?;
+    input.check_done()?;
+    Ok(value)
+}
+
+// no string in the protocol can be longer than this, in utf-16 code units
+pub const MAX_STRING_LENGTH: usize = 32767;
+
+// vanilla counts string lengths in utf-16 code units, so this does too
+// offset is where the string started
+pub fn check_str_len(value: &str, max: usize, offset: usize) -> Result<()> {
+    // every char is at least one code unit, so short enough strings don't need counting
+    if value.len() <= max {
+        return Ok(());
+    }
+    let len = value.encode_utf16().count();
+    if len > max {
+        return Err(Error::new(ErrorKind::TooLong { len, max }, offset));
+    }
+    Ok(())
+}
+
 pub struct ByteReader<'a> {
-    input: &'a [u8]
+    input: &'a [u8],
+    // how much has been read so far, for errors
+    offset: usize,
 }
 
 impl<'a> ByteReader<'a> {
     pub fn new(input: &'a [u8]) -> Self {
-        Self { input }
+        Self { input, offset: 0 }
     }
 
     pub fn read_byte(&mut self) -> Result<u8> {
         match self.input.split_first() {
             Some((byte, input)) => {
                 self.input = input;
+                self.offset += 1;
                 Ok(*byte)
             },
-            None => Err(Error::Eof)
+            None => Err(self.error(ErrorKind::Eof))
         }
     }
 
     pub fn read_bytes(&mut self, len: usize) -> Result<&'a [u8]> {
         if len > self.remaining_len() {
-            return Err(Error::Eof);
+            return Err(self.error(ErrorKind::Eof));
         }
 
         let (res, input) = self.input.split_at(len);
         self.input = input;
+        self.offset += len;
         Ok(res)
     }
 
@@ -45,9 +220,30 @@ impl<'a> ByteReader<'a> {
         self.input.len()
     }
 
+    // the next byte without reading it
+    pub fn peek_byte(&self) -> Option<u8> {
+        self.input.first().copied()
+    }
+
     pub fn done(&self) -> bool {
         self.remaining_len() == 0
     }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn check_done(&self) -> Result<()> {
+        match self.remaining_len() {
+            0 => Ok(()),
+            len => Err(self.error(ErrorKind::TrailingBytes(len))),
+        }
+    }
+
+    // an error at the current offset
+    pub fn error(&self, kind: ErrorKind) -> Error {
+        Error::new(kind, self.offset)
+    }
 }
 
 impl std::io::Read for ByteReader<'_> {
@@ -71,7 +267,16 @@ pub trait Deserialize<'de> {
     fn deserialize(input: &mut ByteReader<'de>) -> Result<Self::Value>;
 }
 
-macro_rules! impl_deserialize_int {
+// enums whose variant is picked by a tag, derived with #[packet(tag_type = ...)]
+// this is what #[packet(switch = ...)] uses when the tag was already read as an earlier field
+pub trait DeserializeVariant<'de>: Sized {
+    type Tag;
+
+    fn deserialize_variant(tag: &Self::Tag, input: &mut ByteReader<'de>) -> Result<Self>;
+}
+
+// everything is big-endian
+macro_rules! impl_deserialize_num {
     ($t: ty, $size: literal) => {
         impl Deserialize<'_> for $t {
             type Value = Self;
@@ -81,25 +286,42 @@ macro_rules! impl_deserialize_int {
 
                 let bytes = input.read_bytes($size)?;
                 let bytes_arr = bytes.try_into().unwrap(); // unwrap is ok, we know it's the right size
-                Ok(<$t>::from_le_bytes(bytes_arr))
+                Ok(<$t>::from_be_bytes(bytes_arr))
             }
         }
     };
 }
 
-impl_deserialize_int!(u8, 1);
-impl_deserialize_int!(i8, 1);
-impl_deserialize_int!(u16, 2);
-impl_deserialize_int!(i16, 2);
-impl_deserialize_int!(u32, 4);
-impl_deserialize_int!(i32, 4);
-impl_deserialize_int!(u64, 6);
-impl_deserialize_int!(i64, 6);
+impl_deserialize_num!(u8, 1);
+impl_deserialize_num!(i8, 1);
+impl_deserialize_num!(u16, 2);
+impl_deserialize_num!(i16, 2);
+impl_deserialize_num!(u32, 4);
+impl_deserialize_num!(i32, 4);
+impl_deserialize_num!(u64, 8);
+impl_deserialize_num!(i64, 8);
+impl_deserialize_num!(u128, 16);
+impl_deserialize_num!(f32, 4);
+impl_deserialize_num!(f64, 8);
+
+impl Deserialize<'_> for bool {
+    type Value = Self;
+
+    fn deserialize(input: &mut ByteReader<'_>) -> Result<Self::Value> {
+        let offset = input.offset();
+        match input.read_byte()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(Error::new(ErrorKind::BadBool, offset))
+        }
+    }
+}
 
 impl<T: num::PrimInt> Deserialize<'_> for VarNumReader<T> {
     type Value = T;
 
     fn deserialize(input: &mut ByteReader<'_>) -> Result<Self::Value> {
+        let offset = input.offset();
         let mut reader = Self::new();
         let res = loop {
             match reader {
@@ -110,17 +332,47 @@ impl<T: num::PrimInt> Deserialize<'_> for VarNumReader<T> {
                 }
             }
         };
-        res.map(|r| r.val).map_err(|_| Error::BadVarNum)
+        res.map(|r| r.val).map_err(|_| Error::new(ErrorKind::BadVarNum, offset))
     }
 }
 
+impl<'de, T: Deserialize<'de>, const N: usize> Deserialize<'de> for [T; N] {
+    type Value = [T::Value; N];
+
+    fn deserialize(input: &mut ByteReader<'de>) -> Result<Self::Value> {
+        use std::convert::TryInto;
+
+        let mut vals = Vec::with_capacity(N);
+        for i in 0..N {
+            vals.push(T::deserialize(input).map_err(|err| err.in_index(i))?);
+        }
+        // can't fail, there's exactly N of them
+        Ok(vals.try_into().ok().unwrap())
+    }
+}
+
+// a string with a different limit than MAX_STRING_LENGTH, like chat json
+pub fn read_str<'de>(input: &mut ByteReader<'de>, max: usize) -> Result<&'de str> {
+    use std::convert::TryFrom;
+
+    let len = VarInt::deserialize(input)?;
+    let len = usize::try_from(len).map_err(|_| input.error(ErrorKind::BadLength))?;
+    // a code unit takes at most 3 bytes, the length gets checked properly by check_str_len
+    if len > max * 3 {
+        return Err(input.error(ErrorKind::TooLong { len, max: max * 3 }));
+    }
+    let offset = input.offset();
+    let bytes = input.read_bytes(len)?;
+    let value = std::str::from_utf8(bytes).map_err(|err| Error::new(ErrorKind::BadUtf8, offset + err.valid_up_to()))?;
+    check_str_len(value, max, offset)?;
+    Ok(value)
+}
+
 impl<'de> Deserialize<'de> for &'de str {
     type Value = &'de str;
 
     fn deserialize(input: &mut ByteReader<'de>) -> Result<Self::Value> {
-        let len = VarInt::deserialize(input)? as usize;
-        let bytes = input.read_bytes(len)?;
-        Ok(std::str::from_utf8(bytes).map_err(|_| Error::BadUtf8)?)
+        read_str(input, MAX_STRING_LENGTH)
     }
 }
 
@@ -130,4 +382,68 @@ impl Deserialize<'_> for String {
     fn deserialize<'de>(input: &mut ByteReader<'de>) -> Result<Self::Value> {
         Ok(<&'de str as Deserialize>::deserialize(input)?.to_owned())
     }
-}
\ No newline at end of file
+}
+// turns a value that borrows from the packet it was read from into one that doesn't,
+// #[packet(owned = "...")] uses this on every field
+pub trait ToOwnedValue {
+    type Owned;
+
+    fn to_owned_value(&self) -> Self::Owned;
+}
+
+// for Copy types, which are already owned
+macro_rules! impl_to_owned_value_copy {
+    ($($t: ty),*) => {
+        $(impl $crate::de::ToOwnedValue for $t {
+            type Owned = Self;
+
+            fn to_owned_value(&self) -> Self {
+                *self
+            }
+        })*
+    };
+}
+
+pub(crate) use impl_to_owned_value_copy;
+
+impl_to_owned_value_copy!(u8, i8, u16, i16, u32, i32, u64, i64, u128, f32, f64, bool);
+
+impl ToOwnedValue for &str {
+    type Owned = String;
+
+    fn to_owned_value(&self) -> String {
+        (*self).to_owned()
+    }
+}
+
+impl ToOwnedValue for &[u8] {
+    type Owned = Vec<u8>;
+
+    fn to_owned_value(&self) -> Vec<u8> {
+        self.to_vec()
+    }
+}
+
+impl ToOwnedValue for String {
+    type Owned = Self;
+
+    fn to_owned_value(&self) -> Self {
+        self.clone()
+    }
+}
+
+impl<T: ToOwnedValue> ToOwnedValue for Vec<T> {
+    type Owned = Vec<T::Owned>;
+
+    fn to_owned_value(&self) -> Self::Owned {
+        self.iter().map(T::to_owned_value).collect()
+    }
+}
+
+impl<T: ToOwnedValue> ToOwnedValue for Option<T> {
+    type Owned = Option<T::Owned>;
+
+    fn to_owned_value(&self) -> Self::Owned {
+        self.as_ref().map(T::to_owned_value)
+    }
+}
@@ -0,0 +1,98 @@
+use std::io;
+use aes::Aes128;
+use cfb8::cipher::{BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use cfb8::cipher::generic_array::GenericArray;
+use rsa::{RsaPrivateKey, Pkcs1v15Encrypt};
+use rsa::pkcs8::EncodePublicKey;
+use sha1::{Sha1, Digest};
+
+// minecraft uses AES-128 in CFB8 mode, with the shared secret as both the key and the iv
+// cfb8 has a block size of one byte, so these can encrypt any amount at a time
+
+pub struct Encryptor(cfb8::Encryptor<Aes128>);
+
+impl Encryptor {
+    pub fn new(key: &[u8; 16], iv: &[u8; 16]) -> Self {
+        Self(cfb8::Encryptor::new(key.into(), iv.into()))
+    }
+
+    pub fn encrypt(&mut self, buf: &mut [u8]) {
+        for byte in buf {
+            self.0.encrypt_block_mut(GenericArray::from_mut_slice(std::slice::from_mut(byte)));
+        }
+    }
+}
+
+pub struct Decryptor(cfb8::Decryptor<Aes128>);
+
+impl Decryptor {
+    pub fn new(key: &[u8; 16], iv: &[u8; 16]) -> Self {
+        Self(cfb8::Decryptor::new(key.into(), iv.into()))
+    }
+
+    pub fn decrypt(&mut self, buf: &mut [u8]) {
+        for byte in buf {
+            self.0.decrypt_block_mut(GenericArray::from_mut_slice(std::slice::from_mut(byte)));
+        }
+    }
+}
+
+// the keypair the server uses during login, only needs to be generated once per server
+pub struct ServerKey {
+    private: RsaPrivateKey,
+    // sent in encryption request, and part of the server id hash
+    public_der: Vec<u8>,
+}
+
+impl ServerKey {
+    pub fn generate() -> io::Result<Self> {
+        let private = RsaPrivateKey::new(&mut rand::rngs::OsRng, 1024)
+            .map_err(|_| io::Error::other("failed to generate server key"))?;
+        let public_der = private.to_public_key().to_public_key_der()
+            .map_err(|_| io::Error::other("failed to encode server public key"))?
+            .into_vec();
+        Ok(Self { private, public_der })
+    }
+
+    // X.509 SubjectPublicKeyInfo, which is what the client expects
+    pub fn public_key_der(&self) -> &[u8] {
+        &self.public_der
+    }
+
+    // for the shared secret and verify token in encryption response
+    pub fn decrypt(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        self.private.decrypt(Pkcs1v15Encrypt, data)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "failed to decrypt with server key"))
+    }
+}
+
+// the serverId sent to the session server
+// minecraft treats the sha1 as a signed big-endian number and prints it in hex,
+// so it can be negative and has no leading zeros
+pub fn server_id_hash(server_id: &str, shared_secret: &[u8], public_key_der: &[u8]) -> String {
+    let mut digest: [u8; 20] = Sha1::new()
+        .chain_update(server_id)
+        .chain_update(shared_secret)
+        .chain_update(public_key_der)
+        .finalize()
+        .into();
+
+    let negative = digest[0] & 0x80 != 0;
+    if negative {
+        // two's complement negation, ripple the +1 up from the lowest byte
+        let mut carry = true;
+        for byte in digest.iter_mut().rev() {
+            let (res, overflow) = (!*byte).overflowing_add(carry as u8);
+            *byte = res;
+            carry = overflow;
+        }
+    }
+
+    let hex: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+    let hex = hex.trim_start_matches('0');
+    if negative {
+        format!("-{}", hex)
+    } else {
+        hex.to_owned()
+    }
+}
@@ -0,0 +1,153 @@
+use std::convert::TryInto;
+use std::time::Duration;
+use tokio::io::{self, AsyncBufRead, AsyncBufReadExt};
+
+// pings from before 1.7, which don't use the usual packet framing
+// they all start with 0xfe, which can't be the start of a modern handshake
+pub enum LegacyPing {
+    // beta 1.8 to 1.3 send just 0xfe
+    Beta,
+    // 1.4 and 1.5 send 0xfe 0x01
+    V1_4,
+    // 1.6 follows that with an MC|PingHost plugin message
+    V1_6 {
+        protocol: u8,
+        hostname: String,
+        port: i32,
+    },
+}
+
+// what goes in the kick packet sent back
+pub struct LegacyServerInfo {
+    pub protocol: i32,
+    pub version: String,
+    pub motd: String,
+    pub online: i32,
+    pub max: i32,
+}
+
+// old clients don't say how much they're going to send, so
+// anything that doesn't show up within this long after the last byte isn't coming
+const READ_WAIT: Duration = Duration::from_millis(100);
+
+const PING_HOST_CHANNEL: &str = "MC|PingHost";
+
+fn read_u16(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(at..at + 2)?.try_into().unwrap()))
+}
+
+fn read_utf16(data: &[u8], at: usize, len: usize) -> Option<String> {
+    let bytes = data.get(at..at + len * 2)?;
+    let units: Vec<u16> = bytes.chunks(2).map(|unit| u16::from_be_bytes([unit[0], unit[1]])).collect();
+    Some(String::from_utf16_lossy(&units))
+}
+
+// parses the 1.6 plugin message, which follows 0xfe 0x01 0xfa
+// None means it isn't all here yet
+fn parse_ping_host(data: &[u8]) -> Option<io::Result<LegacyPing>> {
+    let channel_len = read_u16(data, 0)? as usize;
+    let channel = read_utf16(data, 2, channel_len)?;
+    let data = &data[2 + channel_len * 2..];
+    let payload_len = read_u16(data, 0)? as usize;
+    let payload = data.get(2..2 + payload_len)?;
+    if channel != PING_HOST_CHANNEL {
+        return Some(Err(io::Error::new(io::ErrorKind::InvalidData, "legacy ping has the wrong channel")));
+    }
+
+    let parsed = (|| {
+        let protocol = *payload.first()?;
+        let hostname_len = read_u16(payload, 1)? as usize;
+        let hostname = read_utf16(payload, 3, hostname_len)?;
+        let port_at = 3 + hostname_len * 2;
+        let port = i32::from_be_bytes(payload.get(port_at..port_at + 4)?.try_into().unwrap());
+        Some(LegacyPing::V1_6 { protocol, hostname, port })
+    })();
+    Some(parsed.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed legacy ping")))
+}
+
+// parses everything after the 0xfe
+// None means more might still be coming
+fn parse(data: &[u8]) -> Option<io::Result<LegacyPing>> {
+    match data {
+        // these could be the start of a longer ping
+        [] | [0x01] => None,
+        [0x01, 0xfa, rest @ ..] => parse_ping_host(rest),
+        [0x01, ..] => Some(Ok(LegacyPing::V1_4)),
+        _ => Some(Ok(LegacyPing::Beta)),
+    }
+}
+
+// for when nothing else is coming
+fn parse_incomplete(data: &[u8]) -> LegacyPing {
+    match data {
+        [] => LegacyPing::Beta,
+        // a cut off 1.6 ping also lands here, but 1.6 understands the 1.4 response anyways
+        _ => LegacyPing::V1_4,
+    }
+}
+
+// reads the rest of a legacy ping, after the 0xfe
+pub(crate) async fn read_legacy_ping<R: AsyncBufRead + Unpin>(read: &mut R) -> io::Result<LegacyPing> {
+    let mut data = Vec::new();
+    loop {
+        if let Some(res) = parse(&data) {
+            return res;
+        }
+        match tokio::time::timeout(READ_WAIT, read.fill_buf()).await {
+            Ok(Ok(buf)) if !buf.is_empty() => {
+                let len = buf.len();
+                data.extend_from_slice(buf);
+                read.consume(len);
+            },
+            Ok(Err(err)) => return Err(err),
+            // timed out, or the client is done sending
+            _ => return Ok(parse_incomplete(&data)),
+        }
+    }
+}
+
+// a legacy kick packet, which is also how the server info gets sent
+// the length is a u16, so anything past that many utf-16 units gets cut off
+pub fn kick_packet(message: &str) -> Vec<u8> {
+    let mut units: Vec<u16> = message.encode_utf16().collect();
+    if units.len() > u16::MAX as usize {
+        units.truncate(u16::MAX as usize);
+        // without leaving half a surrogate pair on the end
+        if let Some(0xd800..=0xdbff) = units.last() {
+            units.pop();
+        }
+    }
+    let mut packet = Vec::with_capacity(3 + units.len() * 2);
+    packet.push(0xff);
+    packet.extend_from_slice(&(units.len() as u16).to_be_bytes());
+    for unit in units {
+        packet.extend_from_slice(&unit.to_be_bytes());
+    }
+    packet
+}
+
+// removes § codes along with the character after them
+fn strip_codes(text: &str) -> String {
+    let mut chars = text.chars();
+    let mut stripped = String::with_capacity(text.len());
+    while let Some(c) = chars.next() {
+        if c == '§' {
+            chars.next();
+        } else {
+            stripped.push(c);
+        }
+    }
+    stripped
+}
+
+pub fn server_info_packet(ping: &LegacyPing, info: &LegacyServerInfo) -> Vec<u8> {
+    let message = match ping {
+        // § separates the fields here, so it can't be in the motd
+        LegacyPing::Beta => format!("{}§{}§{}", strip_codes(&info.motd), info.online, info.max),
+        LegacyPing::V1_4 | LegacyPing::V1_6 { .. } => format!(
+            "§1\0{}\0{}\0{}\0{}\0{}",
+            info.protocol, info.version, info.motd, info.online, info.max
+        ),
+    };
+    kick_packet(&message)
+}
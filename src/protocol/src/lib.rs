@@ -1,3 +1,6 @@
+// the derive generates paths through ::mckerel_protocol, so they have to work in here too
+extern crate self as mckerel_protocol;
+
 // has to be at the top because macro scoping is broken
 #[macro_use]
 mod macros;
@@ -6,8 +9,20 @@ pub mod connection;
 pub mod packets;
 pub mod de;
 pub mod ser;
-//pub mod states;
+pub mod encryption;
+pub mod auth;
+pub mod legacy;
+pub mod types;
+pub mod states;
+pub mod versions;
+pub mod nbt;
+pub mod chat;
+#[cfg(feature = "serde_fields")]
+pub mod serde_fields;
 mod varnum;
 
-pub use connection::{Recv, Send, PacketReader, PacketOrLegacyPing, make_conn};
-pub use packets::{serverbound, clientbound, Packet};
\ No newline at end of file
+pub use connection::{Recv, Send, PacketReader, PacketOrLegacyPing, Limits, FrameError, make_conn, enable_compression, enable_encryption};
+pub use packets::{serverbound, clientbound, Packet};
+pub use varnum::{VarInt, VarLong};
+// so packets can be defined outside of this crate, packets_impl! is exported by itself
+pub use macros::{enum_impl, Packet};
\ No newline at end of file
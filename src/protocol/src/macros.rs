@@ -1,5 +1,7 @@
 pub use mckerel_protocol_macros::{enum_impl, Packet};
 
+// the enum of every packet in a state, with dispatch on the packet id
+#[macro_export]
 macro_rules! packets_impl {
     ($name:ident {
         $($type:ident),*
@@ -8,17 +10,39 @@ macro_rules! packets_impl {
             $($type($type)),*
         }
 
-        impl<'de> crate::de::Deserialize<'de> for $name {
+        impl<'de> $crate::de::Deserialize<'de> for $name {
             type Value = Self;
 
-            fn deserialize(input: &mut crate::de::ByteReader<'de>) -> crate::de::Result<Self> {
-                let tag = <crate::varnum::VarInt as crate::de::Deserialize>::deserialize(input)?;
+            fn deserialize(input: &mut $crate::de::ByteReader<'de>) -> $crate::de::Result<Self> {
+                let offset = input.offset();
+                let tag = <$crate::VarInt as $crate::de::Deserialize>::deserialize(input)?;
                 match tag {
-                    $(<$type as crate::Packet>::ID => {
-                        let val = <$type as crate::de::Deserialize<'de>>::deserialize(input)?;
+                    $(<$type as $crate::Packet>::ID => {
+                        let val = <$type as $crate::de::Deserialize<'de>>::deserialize(input)
+                            .and_then(|val| input.check_done().map(|_| val))
+                            .map_err(|err| err.in_packet(stringify!($type), tag))?;
                         Ok(Self::$type(val))
                     }),*
-                    _ => Err(crate::de::Error::BadPacketId)
+                    _ => Err($crate::de::Error::new($crate::de::ErrorKind::BadPacketId(tag), offset))
+                }
+            }
+        }
+
+        $(impl From<$type> for $name {
+            fn from(packet: $type) -> Self {
+                Self::$type(packet)
+            }
+        })*
+
+        impl $crate::ser::Serialize for $name {
+            type Value = Self;
+
+            fn serialize(value: &Self, output: &mut $crate::ser::ByteWriter<'_>) {
+                match value {
+                    $(Self::$type(val) => {
+                        <$crate::VarInt as $crate::ser::Serialize>::serialize(&<$type as $crate::Packet>::ID, output);
+                        <$type as $crate::ser::Serialize>::serialize(val, output);
+                    }),*
                 }
             }
         }
@@ -1,7 +1,9 @@
-use proc_macro;
 use quote::quote;
 use syn::parse::{Parse, ParseStream};
-use syn::{self, braced, parse_macro_input, Ident, LitInt, Token};
+use syn::{self, braced, parse_macro_input, Attribute, Ident, LitInt, Token};
+use darling::FromAttributes;
+
+use crate::parsing;
 
 struct EnumField {
     name: Ident,
@@ -17,14 +19,24 @@ impl Parse for EnumField {
     }
 }
 
+// enum_impl!(#[packet(crate = "...")] VarInt Name { ... }) for a different crate root
+#[derive(FromAttributes)]
+#[darling(attributes(packet))]
+struct EnumAttrs {
+    #[darling(rename = "crate", default = "parsing::default_crate")]
+    krate: syn::Path,
+}
+
 struct EnumInput {
-    tag_type: Ident,
+    attrs: Vec<Attribute>,
+    tag_type: syn::Type,
     name: Ident,
     fields: Vec<EnumField>,
 }
 
 impl Parse for EnumInput {
     fn parse(input: ParseStream) -> syn::Result<Self> {
+        let attrs = input.call(Attribute::parse_outer)?;
         let tag_type = input.parse()?;
         let name = input.parse()?;
         let fields;
@@ -32,6 +44,7 @@ impl Parse for EnumInput {
         let fields = fields.parse_terminated::<_, Token![,]>(Parse::parse)?;
         let fields = fields.into_iter().collect();
         Ok(Self {
+            attrs,
             tag_type,
             name,
             fields,
@@ -41,6 +54,10 @@ impl Parse for EnumInput {
 
 pub fn enum_impl(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = parse_macro_input!(input as EnumInput);
+    let krate = match EnumAttrs::from_attributes(&input.attrs) {
+        Ok(attrs) => attrs.krate,
+        Err(err) => return err.write_errors().into(),
+    };
 
     let tag_type = input.tag_type;
     let enum_name = input.name;
@@ -48,20 +65,42 @@ pub fn enum_impl(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let field_names: Vec<&Ident> = input.fields.iter().map(|f| &f.name).collect();
 
     (quote! {
+        // comparable so it can be used in when expressions
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
         pub enum #enum_name {
             #(#field_names),*
         }
 
-        impl crate::de::Deserialize<'_> for #enum_name {
+        impl #krate::de::Deserialize<'_> for #enum_name {
             type Value = Self;
 
-            fn deserialize(input: &mut crate::de::ByteReader<'_>) -> crate::de::Result<Self> {
-                let tag = <#tag_type as crate::de::Deserialize>::deserialize(input)?;
+            fn deserialize(input: &mut #krate::de::ByteReader<'_>) -> #krate::de::Result<Self> {
+                let offset = input.offset();
+                let tag = <#tag_type as #krate::de::Deserialize>::deserialize(input)?;
                 match tag {
                     #(#tag_vals => Ok(Self::#field_names),)*
-                    default => Err(crate::de::Error::BadEnumTag)
+                    default => Err(#krate::de::Error::new(#krate::de::ErrorKind::BadEnumTag, offset))
                 }
             }
         }
+
+        impl #krate::de::ToOwnedValue for #enum_name {
+            type Owned = Self;
+
+            fn to_owned_value(&self) -> Self {
+                *self
+            }
+        }
+
+        impl #krate::ser::Serialize for #enum_name {
+            type Value = Self;
+
+            fn serialize(value: &Self, output: &mut #krate::ser::ByteWriter<'_>) {
+                let tag = match value {
+                    #(Self::#field_names => #tag_vals,)*
+                };
+                <#tag_type as #krate::ser::Serialize>::serialize(&tag, output);
+            }
+        }
     }).into()
 }
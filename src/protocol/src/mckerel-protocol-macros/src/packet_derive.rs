@@ -1,85 +1,432 @@
-use proc_macro;
 use quote::{quote, format_ident};
 use syn::{self, parse_macro_input, DeriveInput};
+use syn::ext::IdentExt;
 use darling::FromDeriveInput;
 
-use crate::parsing::{self, Input};
+use crate::parsing::{self, Input, Data, Fields, FieldKind};
 
-fn named_struct_deserialize_impl(struct_name: &syn::Ident, fields: &Vec<parsing::Field>) -> proc_macro2::TokenStream {
-    let field_names = fields.iter().map(|field| field.ident.as_ref().unwrap());
-    let field_withs = fields.iter().map(|field| {
-        match &field.attrs.with {
-            Some(ty) => {
-                let ty = format_ident!("{}", ty);
-                quote! { #ty }
-            },
-            None => {
-                let ty = &field.ty;
-                quote! { #ty }
+// the most elements a length prefixed Vec reserves room for before reading them
+const MAX_RESERVED_ELEMENTS: usize = 1024;
+
+fn is_u8(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(path) => path.path.is_ident("u8"),
+        _ => false,
+    }
+}
+
+// earlier fields are visible to when and switch expressions as references,
+// in both directions so the same expression works for both
+fn earlier_refs(earlier: &[&syn::Ident]) -> proc_macro2::TokenStream {
+    quote! {
+        #(#[allow(unused_variables)] let #earlier = &#earlier;)*
+    }
+}
+
+// an expression that reads the field, errors get name added to their path
+fn field_deserialize(krate: &syn::Path, field: &parsing::Field, name: &str, earlier: &[&syn::Ident]) -> proc_macro2::TokenStream {
+    let read = kind_deserialize(krate, &field.kind, field.max_len, earlier);
+    let read = match &field.when {
+        Some(when) => {
+            let refs = earlier_refs(earlier);
+            quote! {
+                if { #refs #when } {
+                    #read.map(Some)
+                } else {
+                    Ok(None)
+                }
+            }
+        },
+        None => read,
+    };
+    quote! {
+        #krate::de::field(#name, || #read)?
+    }
+}
+
+// reads a string and checks it isn't longer than max_len
+fn str_deserialize(krate: &syn::Path, ty: &syn::Type, max_len: usize) -> proc_macro2::TokenStream {
+    quote! {{
+        let offset = input.offset();
+        let value = <#ty as #krate::de::Deserialize<'de>>::deserialize(input)?;
+        #krate::de::check_str_len(&value, #max_len, offset).map(|_| value)
+    }}
+}
+
+// an error if the length that was just read is above max_len
+fn len_check(krate: &syn::Path, max_len: Option<usize>) -> proc_macro2::TokenStream {
+    match max_len {
+        Some(max) => quote! {
+            if len > #max {
+                return Err(input.error(#krate::de::ErrorKind::TooLong { len, max: #max }));
+            }
+        },
+        None => quote! {},
+    }
+}
+
+// an expression giving a de::Result of the field
+fn kind_deserialize(krate: &syn::Path, kind: &FieldKind, max_len: Option<usize>, earlier: &[&syn::Ident]) -> proc_macro2::TokenStream {
+    let len_check = len_check(krate, max_len);
+    match kind {
+        FieldKind::Plain(ty) => match max_len {
+            Some(max_len) => str_deserialize(krate, ty, max_len),
+            None => quote! {
+                <#ty as #krate::de::Deserialize<'de>>::deserialize(input)
             },
+        },
+        FieldKind::Prefixed { len, elem, borrowed } if is_u8(elem) => {
+            let to_vec = if *borrowed { quote! {} } else { quote! { .map(<[u8]>::to_vec) } };
+            quote! {{
+                let len = <#len as #krate::de::Deserialize<'de>>::deserialize(input)?;
+                let len = <usize as std::convert::TryFrom<_>>::try_from(len).map_err(|_| input.error(#krate::de::ErrorKind::BadLength))?;
+                #len_check
+                input.read_bytes(len)#to_vec
+            }}
+        },
+        FieldKind::Prefixed { len, elem, .. } => quote! {{
+            let len = <#len as #krate::de::Deserialize<'de>>::deserialize(input)?;
+            let len = <usize as std::convert::TryFrom<_>>::try_from(len).map_err(|_| input.error(#krate::de::ErrorKind::BadLength))?;
+            #len_check
+            // the length hasn't been checked yet, so don't preallocate more than could possibly be there
+            let elem_size = std::cmp::max(1, std::mem::size_of::<<#elem as #krate::de::Deserialize<'de>>::Value>());
+            let mut vec = Vec::with_capacity(len.min(input.remaining_len() / elem_size).min(#MAX_RESERVED_ELEMENTS));
+            for i in 0..len {
+                vec.push(<#elem as #krate::de::Deserialize<'de>>::deserialize(input).map_err(|err| err.in_index(i))?);
+            }
+            Ok(vec)
+        }},
+        FieldKind::Optional(ty) => {
+            let read = match max_len {
+                Some(max_len) => str_deserialize(krate, ty, max_len),
+                None => quote! { <#ty as #krate::de::Deserialize<'de>>::deserialize(input) },
+            };
+            quote! {
+                if <bool as #krate::de::Deserialize<'de>>::deserialize(input)? {
+                    #read.map(Some)
+                } else {
+                    Ok(None)
+                }
+            }
+        },
+        FieldKind::Rest { borrowed: true, .. } => quote! {
+            input.read_bytes(input.remaining_len())
+        },
+        FieldKind::Rest { elem, .. } if is_u8(elem) => quote! {
+            input.read_bytes(input.remaining_len()).map(<[u8]>::to_vec)
+        },
+        FieldKind::Rest { elem, .. } => quote! {{
+            let mut vec = Vec::new();
+            while !input.done() {
+                let i = vec.len();
+                vec.push(<#elem as #krate::de::Deserialize<'de>>::deserialize(input).map_err(|err| err.in_index(i))?);
+            }
+            Ok(vec)
+        }},
+        FieldKind::Switch { ty, tag } => {
+            let refs = earlier_refs(earlier);
+            quote! {
+                <#ty as #krate::de::DeserializeVariant<'de>>::deserialize_variant({ #refs #tag }, input)
+            }
+        },
+    }
+}
+
+// a statement that writes the field, value is a reference to it
+// all the fields are in scope as references, like earlier_refs
+fn field_serialize(krate: &syn::Path, field: &parsing::Field, value: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    match &field.when {
+        Some(when) => {
+            let write = kind_serialize(krate, &field.kind, quote! { inner });
+            let message = format!("`{}` has to be set when `{}`", value, quote! { #when });
+            quote! {
+                if #when {
+                    match #value {
+                        Some(inner) => { #write }
+                        None => panic!(#message),
+                    }
+                }
+            }
+        },
+        None => kind_serialize(krate, &field.kind, value),
+    }
+}
+
+fn kind_serialize(krate: &syn::Path, kind: &FieldKind, value: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    match kind {
+        FieldKind::Plain(ty) => quote! {
+            <#ty as #krate::ser::Serialize>::serialize(#value, output);
+        },
+        FieldKind::Prefixed { len, elem, .. } => {
+            let write_elems = if is_u8(elem) {
+                quote! { output.write_bytes(vec); }
+            } else {
+                quote! {
+                    for elem in vec.iter() {
+                        <#elem as #krate::ser::Serialize>::serialize(elem, output);
+                    }
+                }
+            };
+            quote! {{
+                let vec = #value;
+                let len: <#len as #krate::ser::Serialize>::Value = std::convert::TryFrom::try_from(vec.len())
+                    .expect("too many elements for the length prefix");
+                <#len as #krate::ser::Serialize>::serialize(&len, output);
+                #write_elems
+            }}
+        },
+        FieldKind::Optional(ty) => quote! {
+            match #value {
+                Some(inner) => {
+                    <bool as #krate::ser::Serialize>::serialize(&true, output);
+                    <#ty as #krate::ser::Serialize>::serialize(inner, output);
+                },
+                None => <bool as #krate::ser::Serialize>::serialize(&false, output),
+            }
+        },
+        FieldKind::Rest { elem, .. } if is_u8(elem) => quote! {
+            output.write_bytes(#value);
+        },
+        FieldKind::Rest { elem, .. } => quote! {
+            for elem in (#value).iter() {
+                <#elem as #krate::ser::Serialize>::serialize(elem, output);
+            }
+        },
+        FieldKind::Switch { ty, tag } => quote! {{
+            debug_assert!(&<#ty as #krate::ser::SerializeVariant>::tag(#value) == #tag, "switch field doesn't match its tag");
+            <#ty as #krate::ser::SerializeVariant>::serialize_variant(#value, output);
+        }},
+    }
+}
+
+fn unnamed_ident(i: usize) -> syn::Ident {
+    format_ident!("field_{}", i)
+}
+
+// an expression that reads the fields and builds them with ctor (Self or Self::Variant)
+fn fields_deserialize(krate: &syn::Path, fields: &Fields, ctor: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    match fields {
+        Fields::Named(fields) => {
+            let names: Vec<_> = fields.iter().map(|field| field.ident.as_ref().unwrap()).collect();
+            let reads = fields.iter().enumerate().map(|(i, field)| field_deserialize(krate, field, &names[i].unraw().to_string(), &names[..i]));
+            quote! {{
+                #(let #names = #reads;)*
+                #ctor { #(#names),* }
+            }}
+        },
+        Fields::Unnamed(fields) => {
+            let reads = fields.iter().enumerate().map(|(i, field)| field_deserialize(krate, field, &i.to_string(), &[]));
+            quote! {
+                #ctor(#(#reads),*)
+            }
+        },
+        Fields::Unit => ctor,
+    }
+}
+
+// a pattern binding all the fields by reference, and the statements that write them
+fn fields_serialize(krate: &syn::Path, fields: &Fields, path: proc_macro2::TokenStream) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
+    match fields {
+        Fields::Named(fields) => {
+            let names = fields.iter().map(|field| field.ident.as_ref().unwrap());
+            let writes = fields.iter().map(|field| {
+                let name = field.ident.as_ref().unwrap();
+                field_serialize(krate, field, quote! { #name })
+            });
+            (quote! { #path { #(#names),* } }, quote! { #(#writes)* })
+        },
+        Fields::Unnamed(fields) => {
+            let names = (0..fields.len()).map(unnamed_ident);
+            let writes = fields.iter().enumerate().map(|(i, field)| {
+                let name = unnamed_ident(i);
+                field_serialize(krate, field, quote! { #name })
+            });
+            (quote! { #path(#(#names),*) }, quote! { #(#writes)* })
+        },
+        Fields::Unit => (path, quote! {}),
+    }
+}
+
+// the type being derived for, and the generics that impls other than Deserialize need for it
+struct Target {
+    ty: proc_macro2::TokenStream,
+    generics: proc_macro2::TokenStream,
+}
+
+impl Target {
+    fn new(input: &Input) -> Self {
+        let ident = &input.ident;
+        // Deserialize impls declare 'de themselves, so it's the same lifetime as the one borrowed from
+        let generics = if input.borrows { quote! { <'de> } } else { quote! {} };
+        Self {
+            ty: quote! { #ident #generics },
+            generics,
         }
-    });
+    }
+}
 
-    (quote! {
-        impl<'de> crate::de::Deserialize<'de> for #struct_name {
+fn struct_impls(krate: &syn::Path, target: &Target, fields: &Fields) -> proc_macro2::TokenStream {
+    let Target { ty: name, generics } = target;
+    let read = fields_deserialize(krate, fields, quote! { Self });
+    let (pattern, writes) = fields_serialize(krate, fields, quote! { Self });
+
+    quote! {
+        impl<'de> #krate::de::Deserialize<'de> for #name {
             type Value = Self;
 
-            fn deserialize(input: &mut crate::de::ByteReader<'de>) -> crate::de::Result<Self> {
-                Ok(Self {
-                    #(#field_names: <#field_withs as crate::de::Deserialize<'de>>::deserialize(input)?),*
-                })
+            fn deserialize(input: &mut #krate::de::ByteReader<'de>) -> #krate::de::Result<Self> {
+                Ok(#read)
             }
         }
-    }).into()
+
+        impl #generics #krate::ser::Serialize for #name {
+            type Value = Self;
+
+            fn serialize(value: &Self, output: &mut #krate::ser::ByteWriter<'_>) {
+                let #pattern = value;
+                #writes
+            }
+        }
+    }
 }
 
-fn unnamed_struct_deserialize_impl(struct_name: &syn::Ident, fields: &Vec<parsing::Field>) -> proc_macro2::TokenStream {
-    let field_withs = fields.iter().map(|field| {
-        match &field.attrs.with {
-            Some(ty) => {
-                let ty = format_ident!("{}", ty);
-                quote! { #ty }
-            },
-            None => {
-                let ty = &field.ty;
-                quote! { #ty }
-            },
+fn enum_impls(krate: &syn::Path, target: &Target, tag_type: &syn::Type, variants: &[parsing::Variant]) -> proc_macro2::TokenStream {
+    let Target { ty: name, generics } = target;
+    let tags: Vec<_> = variants.iter().map(|variant| proc_macro2::Literal::i64_unsuffixed(variant.tag)).collect();
+    // errors in a variant's fields get the variant's name in their path
+    let reads = variants.iter().map(|variant| {
+        let ident = &variant.ident;
+        let read = fields_deserialize(krate, &variant.fields, quote! { Self::#ident });
+        let name = ident.to_string();
+        quote! {
+            #krate::de::field(#name, || Ok(#read))
         }
     });
+    let (patterns, writes): (Vec<_>, Vec<_>) = variants.iter().map(|variant| {
+        let ident = &variant.ident;
+        fields_serialize(krate, &variant.fields, quote! { Self::#ident })
+    }).unzip();
 
-    (quote! {
-        impl<'de> crate::de::Deserialize<'de> for #struct_name {
+    quote! {
+        impl<'de> #krate::de::DeserializeVariant<'de> for #name {
+            type Tag = <#tag_type as #krate::de::Deserialize<'de>>::Value;
+
+            fn deserialize_variant(tag: &Self::Tag, input: &mut #krate::de::ByteReader<'de>) -> #krate::de::Result<Self> {
+                match *tag {
+                    #(#tags => #reads,)*
+                    _ => Err(input.error(#krate::de::ErrorKind::BadEnumTag)),
+                }
+            }
+        }
+
+        impl<'de> #krate::de::Deserialize<'de> for #name {
             type Value = Self;
 
-            fn deserialize(input: &mut crate::de::ByteReader<'de>) -> crate::de::Result<Self> {
-                Ok(Self(
-                    #(<#field_withs as crate::de::Deserialize<'de>>::deserialize(input)?),*
-                ))
+            fn deserialize(input: &mut #krate::de::ByteReader<'de>) -> #krate::de::Result<Self> {
+                let tag = <#tag_type as #krate::de::Deserialize<'de>>::deserialize(input)?;
+                <Self as #krate::de::DeserializeVariant<'de>>::deserialize_variant(&tag, input)
             }
         }
-    }).into()
-}
 
-fn unit_struct_deserialize_impl(struct_name: &syn::Ident) -> proc_macro2::TokenStream {
-    (quote! {
-        impl crate::de::Deserialize<'_> for #struct_name {
+        impl #generics #krate::ser::SerializeVariant for #name {
+            type Tag = <#tag_type as #krate::ser::Serialize>::Value;
+
+            #[allow(unused_variables)]
+            fn tag(value: &Self) -> Self::Tag {
+                match value {
+                    #(#patterns => #tags,)*
+                }
+            }
+
+            fn serialize_variant(value: &Self, output: &mut #krate::ser::ByteWriter<'_>) {
+                match value {
+                    #(#patterns => { #writes })*
+                }
+            }
+        }
+
+        impl #generics #krate::ser::Serialize for #name {
             type Value = Self;
 
-            fn deserialize(input: &mut crate::de::ByteReader<'_>) -> crate::de::Result<Self> {
-                Ok(Self)
+            fn serialize(value: &Self, output: &mut #krate::ser::ByteWriter<'_>) {
+                <#tag_type as #krate::ser::Serialize>::serialize(&<Self as #krate::ser::SerializeVariant>::tag(value), output);
+                <Self as #krate::ser::SerializeVariant>::serialize_variant(value, output);
             }
         }
-    }).into()
+    }
 }
 
-fn packet_impl(input: &Input) -> proc_macro2::TokenStream {
-    let struct_name = &input.ident;
-    let id = input.attrs.id;
-    (quote! {
-        impl crate::Packet for #struct_name {
-            const ID: i32 = #id;
+// builds path out of the fields bound by the pattern from fields_serialize, converting each one
+fn fields_to_owned(krate: &syn::Path, fields: &Fields, path: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    match fields {
+        Fields::Named(fields) => {
+            let names = fields.iter().map(|field| field.ident.as_ref().unwrap());
+            let values = names.clone();
+            quote! { #path { #(#names: #krate::de::ToOwnedValue::to_owned_value(#values)),* } }
+        },
+        Fields::Unnamed(fields) => {
+            let names = (0..fields.len()).map(unnamed_ident);
+            quote! { #path(#(#krate::de::ToOwnedValue::to_owned_value(#names)),*) }
+        },
+        Fields::Unit => path,
+    }
+}
+
+// #[packet(owned = "...")], for borrowing packets that have a version without borrows
+// it has to have the same fields, with types the fields here convert into
+fn owned_impls(krate: &syn::Path, target: &Target, owned: &syn::Path, data: &Data) -> proc_macro2::TokenStream {
+    let Target { ty: name, generics } = target;
+    let convert = match data {
+        Data::Struct(fields) => {
+            let (pattern, _) = fields_serialize(krate, fields, quote! { Self });
+            let build = fields_to_owned(krate, fields, quote! { #owned });
+            quote! {
+                let #pattern = self;
+                #build
+            }
+        },
+        Data::Enum { variants, .. } => {
+            let (patterns, builds): (Vec<_>, Vec<_>) = variants.iter().map(|variant| {
+                let ident = &variant.ident;
+                let (pattern, _) = fields_serialize(krate, &variant.fields, quote! { Self::#ident });
+                (pattern, fields_to_owned(krate, &variant.fields, quote! { #owned::#ident }))
+            }).unzip();
+            quote! {
+                match self {
+                    #(#patterns => #builds,)*
+                }
+            }
+        },
+    };
+
+    quote! {
+        impl #generics #krate::de::ToOwnedValue for #name {
+            type Owned = #owned;
+
+            fn to_owned_value(&self) -> #owned {
+                #convert
+            }
         }
-    }).into()
+
+        impl #generics #name {
+            pub fn to_owned(&self) -> #owned {
+                #krate::de::ToOwnedValue::to_owned_value(self)
+            }
+        }
+    }
+}
+
+fn packet_impl(input: &Input, target: &Target) -> proc_macro2::TokenStream {
+    let Target { ty: name, generics } = target;
+    let krate = &input.attrs.krate;
+    match input.attrs.id {
+        Some(id) => quote! {
+            impl #generics #krate::Packet for #name {
+                const ID: i32 = #id;
+            }
+        },
+        None => quote! {},
+    }
 }
 
 pub fn packet_derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
@@ -89,16 +436,22 @@ pub fn packet_derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream
         Err(err) => return err.write_errors().into(),
     };
 
-    let struct_name = &input.ident;
-    let deserialize_impl = match &input.data {
-        parsing::DataStruct::Named(fields) => named_struct_deserialize_impl(struct_name, fields),
-        parsing::DataStruct::Unnamed(fields) => unnamed_struct_deserialize_impl(struct_name, fields),
-        parsing::DataStruct::Unit => unit_struct_deserialize_impl(struct_name),
+    let krate = &input.attrs.krate;
+    let target = Target::new(&input);
+    let impls = match &input.data {
+        Data::Struct(fields) => struct_impls(krate, &target, fields),
+        Data::Enum { tag_type, variants } => enum_impls(krate, &target, tag_type, variants),
+    };
+    let packet_impl = packet_impl(&input, &target);
+    let owned_impls = match &input.owned {
+        Some(owned) => owned_impls(krate, &target, owned, &input.data),
+        None => quote! {},
     };
-    let packet_impl = packet_impl(&input);
     (quote! {
-        #deserialize_impl
+        #impls
 
         #packet_impl
+
+        #owned_impls
     }).into()
-}
\ No newline at end of file
+}
@@ -1,73 +1,270 @@
-use syn::{self, Type, Ident, DeriveInput};
+use syn::{self, Type, Expr, Ident, DeriveInput};
 use syn::spanned::Spanned;
-use darling::{self, FromAttributes, FromField, FromDeriveInput};
+use darling::{self, FromAttributes, FromDeriveInput};
 
 #[derive(FromAttributes)]
-#[darling(attributes(darling))]
+#[darling(attributes(packet))]
 pub struct FieldAttrs {
     #[darling(default)]
     pub with: Option<String>,
+    #[darling(default)]
+    pub len: Option<String>,
+    #[darling(default)]
+    pub optional: bool,
+    #[darling(default)]
+    pub rest: bool,
+    // an expression over the earlier fields saying whether the field is there
+    #[darling(default)]
+    pub when: Option<String>,
+    // an expression over the earlier fields giving the tag of a switch enum
+    #[darling(default)]
+    pub switch: Option<String>,
+    // the most characters a string, or elements a len field, can have
+    #[darling(default)]
+    pub max_len: Option<usize>,
+}
+
+// how a field gets read and written
+// the types here are what's actually used for the (de)serializing,
+// so they're the with type if there is one
+pub enum FieldKind {
+    Plain(Type),
+    // a Vec<T>, prefixed with its length
+    // borrowed means it's a &'de [u8] instead of a Vec<u8>
+    Prefixed { len: Box<Type>, elem: Type, borrowed: bool },
+    // an Option<T>, prefixed with a bool saying whether it's there
+    Optional(Type),
+    // a Vec<T> that takes up the rest of the packet, or a &'de [u8]
+    Rest { elem: Type, borrowed: bool },
+    // an enum whose variant is picked by a tag that was already read
+    Switch { ty: Type, tag: Box<Expr> },
 }
 
 pub struct Field {
     pub ident: Option<Ident>,
-    pub ty: Type,
-    pub attrs: FieldAttrs,
+    pub kind: FieldKind,
+    // if there's a condition the field is an Option of whatever kind says
+    pub when: Option<Expr>,
+    pub max_len: Option<usize>,
+}
+
+fn parse_type(ty: &str, span: &impl Spanned) -> darling::Result<Type> {
+    syn::parse_str(ty).map_err(|_| darling::Error::custom(format!("`{}` isn't a type", ty)).with_span(span))
+}
+
+fn parse_expr(expr: &str, span: &impl Spanned) -> darling::Result<Expr> {
+    syn::parse_str(expr).map_err(|_| darling::Error::custom(format!("`{}` isn't an expression", expr)).with_span(span))
+}
+
+// gets T out of wrapper<T>
+fn generic_arg(ty: &Type, wrapper: &str) -> Option<Type> {
+    let segment = match ty {
+        Type::Path(path) => path.path.segments.last()?,
+        _ => return None,
+    };
+    if segment.ident != wrapper {
+        return None;
+    }
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) if args.args.len() == 1 => match &args.args[0] {
+            syn::GenericArgument::Type(ty) => Some(ty.clone()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// &[u8], which can be used instead of a Vec<u8> to borrow from the packet
+fn is_byte_slice(ty: &Type) -> bool {
+    match ty {
+        Type::Reference(reference) if reference.mutability.is_none() => match &*reference.elem {
+            Type::Slice(slice) => matches!(&*slice.elem, Type::Path(path) if path.path.is_ident("u8")),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+// with applies to the elements of a Vec or the inside of an Option
+fn inner_type(ty: &Type, with: Option<Type>, wrapper: &str, attr: &str) -> darling::Result<Type> {
+    match (with, generic_arg(ty, wrapper)) {
+        (Some(with), _) => Ok(with),
+        (None, Some(inner)) => Ok(inner),
+        (None, None) => {
+            let article = if wrapper == "Option" { "an" } else { "a" };
+            Err(darling::Error::custom(format!("`{}` fields have to be {} `{}<_>`", attr, article, wrapper)).with_span(ty))
+        },
+    }
+}
+
+impl FieldKind {
+    // ty is the type that's actually there, so the inside of the Option for conditional fields
+    fn new(ty: &Type, attrs: &FieldAttrs) -> darling::Result<Self> {
+        let with = attrs.with.as_ref().map(|with| parse_type(with, ty)).transpose()?;
+        match (&attrs.len, attrs.optional, attrs.rest, &attrs.switch) {
+            (None, false, false, None) => Ok(Self::Plain(with.unwrap_or_else(|| ty.clone()))),
+            (Some(len), false, false, None) if is_byte_slice(ty) => Ok(Self::Prefixed {
+                len: Box::new(parse_type(len, ty)?),
+                elem: syn::parse_quote!(u8),
+                borrowed: true,
+            }),
+            (Some(len), false, false, None) => Ok(Self::Prefixed {
+                len: Box::new(parse_type(len, ty)?),
+                elem: inner_type(ty, with, "Vec", "len")?,
+                borrowed: false,
+            }),
+            (None, true, false, None) => Ok(Self::Optional(inner_type(ty, with, "Option", "optional")?)),
+            (None, false, true, None) if is_byte_slice(ty) => Ok(Self::Rest { elem: syn::parse_quote!(u8), borrowed: true }),
+            (None, false, true, None) => Ok(Self::Rest { elem: inner_type(ty, with, "Vec", "rest")?, borrowed: false }),
+            (None, false, false, Some(tag)) => Ok(Self::Switch {
+                ty: with.unwrap_or_else(|| ty.clone()),
+                tag: Box::new(parse_expr(tag, ty)?),
+            }),
+            _ => Err(darling::Error::custom("only one of `len`, `optional`, `rest` and `switch` can be used").with_span(ty)),
+        }
+    }
 }
 
-// apparently darling's derive macros can't map on attrs?
-impl FromField for Field {
-    fn from_field(field: &syn::Field) -> darling::Result<Self> {
+impl Field {
+    fn new(field: &syn::Field, named: bool) -> darling::Result<Self> {
+        let attrs = FieldAttrs::from_attributes(&field.attrs)?;
+        let (ty, when) = match &attrs.when {
+            Some(_) if !named => return Err(darling::Error::custom("`when` can only be used on named fields").with_span(&field.ty)),
+            Some(when) => (inner_type(&field.ty, None, "Option", "when")?, Some(parse_expr(when, &field.ty)?)),
+            None => (field.ty.clone(), None),
+        };
+        if attrs.switch.is_some() && !named {
+            return Err(darling::Error::custom("`switch` can only be used on named fields").with_span(&field.ty));
+        }
+        let kind = FieldKind::new(&ty, &attrs)?;
+        if attrs.max_len.is_some() && matches!(kind, FieldKind::Rest { .. } | FieldKind::Switch { .. }) {
+            return Err(darling::Error::custom("`max_len` can't be used with `rest` or `switch`").with_span(&field.ty));
+        }
         Ok(Self {
             ident: field.ident.clone(),
-            ty: field.ty.clone(),
-            attrs: FieldAttrs::from_attributes(&field.attrs)?
+            kind,
+            when,
+            max_len: attrs.max_len,
         })
     }
 }
 
-pub enum DataStruct {
+pub enum Fields {
     Named(Vec<Field>),
     Unnamed(Vec<Field>),
     Unit,
 }
 
-impl DataStruct {
-    fn new(data_struct: &syn::DataStruct) -> darling::Result<Self> {
-        match &data_struct.fields {
-            syn::Fields::Named(fields) => Ok(Self::Named(fields.named.iter().map(Field::from_field).collect::<darling::Result<_>>()?)),
-            syn::Fields::Unnamed(fields) => Ok(Self::Unnamed(fields.unnamed.iter().map(Field::from_field).collect::<darling::Result<_>>()?)),
+impl Fields {
+    fn new(fields: &syn::Fields) -> darling::Result<Self> {
+        match fields {
+            syn::Fields::Named(fields) => Ok(Self::Named(fields.named.iter().map(|field| Field::new(field, true)).collect::<darling::Result<_>>()?)),
+            syn::Fields::Unnamed(fields) => Ok(Self::Unnamed(fields.unnamed.iter().map(|field| Field::new(field, false)).collect::<darling::Result<_>>()?)),
             syn::Fields::Unit => Ok(Self::Unit)
         }
     }
+}
+
+#[derive(FromAttributes)]
+#[darling(attributes(packet))]
+pub struct VariantAttrs {
+    pub tag: i64,
+}
 
-    fn from_data(data: &syn::Data) -> darling::Result<Self> {
-        match data {
-            syn::Data::Struct(data_struct) => Ok(Self::new(data_struct)?),
-            syn::Data::Enum(data_enum) => Err(syn::Error::new(data_enum.enum_token.span(), "expected struct for packet derive").into()),
-            syn::Data::Union(data_union) => Err(syn::Error::new(data_union.union_token.span(), "expected struct for packet derive").into())
+pub struct Variant {
+    pub ident: Ident,
+    pub tag: i64,
+    pub fields: Fields,
+}
+
+impl Variant {
+    fn new(variant: &syn::Variant) -> darling::Result<Self> {
+        Ok(Self {
+            ident: variant.ident.clone(),
+            tag: VariantAttrs::from_attributes(&variant.attrs).map_err(|err| err.with_span(&variant.ident))?.tag,
+            fields: Fields::new(&variant.fields)?,
+        })
+    }
+}
+
+pub enum Data {
+    Struct(Fields),
+    // the variant is picked by a tag, which is written before it unless it's used as a switch field
+    Enum { tag_type: Box<Type>, variants: Vec<Variant> },
+}
+
+impl Data {
+    fn new(data: &syn::Data, attrs: &StructAttrs) -> darling::Result<Self> {
+        match (data, &attrs.tag_type) {
+            (syn::Data::Struct(data_struct), None) => Ok(Self::Struct(Fields::new(&data_struct.fields)?)),
+            (syn::Data::Struct(data_struct), Some(_)) => Err(syn::Error::new(data_struct.struct_token.span(), "`tag_type` can only be used on enums").into()),
+            (syn::Data::Enum(data_enum), Some(tag_type)) => Ok(Self::Enum {
+                tag_type: Box::new(parse_type(tag_type, &data_enum.enum_token)?),
+                variants: data_enum.variants.iter().map(Variant::new).collect::<darling::Result<_>>()?,
+            }),
+            (syn::Data::Enum(data_enum), None) => Err(syn::Error::new(data_enum.enum_token.span(), "enums need a `tag_type` for packet derive").into()),
+            (syn::Data::Union(data_union), _) => Err(syn::Error::new(data_union.union_token.span(), "expected struct or enum for packet derive").into())
         }
     }
 }
 
+// where the generated code finds mckerel_protocol, for when it's renamed or reexported
+pub fn default_crate() -> syn::Path {
+    syn::parse_quote!(::mckerel_protocol)
+}
+
 #[derive(FromAttributes)]
 #[darling(attributes(packet))]
 pub struct StructAttrs {
-    pub id: i32,
+    // without an id it's just something that can be used as a field
+    #[darling(default)]
+    pub id: Option<i32>,
+    #[darling(default)]
+    pub tag_type: Option<String>,
+    #[darling(rename = "crate", default = "default_crate")]
+    pub krate: syn::Path,
+    // the type without borrows this converts into with to_owned
+    #[darling(default)]
+    pub owned: Option<String>,
 }
 
 pub struct Input {
     pub ident: Ident,
-    pub data: DataStruct,
+    pub data: Data,
     pub attrs: StructAttrs,
+    // whether there's a 'de lifetime for borrowing from the packet
+    pub borrows: bool,
+    pub owned: Option<syn::Path>,
+}
+
+// the only generic allowed is the lifetime of the bytes it's read from,
+// which has to be called 'de since that's what the Deserialize impl uses
+fn borrows(generics: &syn::Generics) -> darling::Result<bool> {
+    let mut borrows = false;
+    for param in &generics.params {
+        match param {
+            syn::GenericParam::Lifetime(lifetime) if lifetime.lifetime.ident == "de" && !borrows => borrows = true,
+            _ => return Err(darling::Error::custom("packets can only have one generic, a `'de` lifetime").with_span(param)),
+        }
+    }
+    if let Some(where_clause) = &generics.where_clause {
+        return Err(darling::Error::custom("packets can't have a where clause").with_span(where_clause));
+    }
+    Ok(borrows)
 }
 
 impl FromDeriveInput for Input {
     fn from_derive_input(input: &DeriveInput) -> darling::Result<Self> {
+        let attrs = StructAttrs::from_attributes(&input.attrs)?;
+        let owned = attrs.owned.as_ref().map(|owned| {
+            syn::parse_str(owned).map_err(|_| darling::Error::custom(format!("`{}` isn't a path", owned)).with_span(&input.ident))
+        }).transpose()?;
         Ok(Self {
             ident: input.ident.clone(),
-            data: DataStruct::from_data(&input.data)?,
-            attrs: StructAttrs::from_attributes(&input.attrs)?,
+            data: Data::new(&input.data, &attrs)?,
+            borrows: borrows(&input.generics)?,
+            owned,
+            attrs,
         })
     }
 }
\ No newline at end of file
@@ -0,0 +1,331 @@
+// the binary form, big-endian like everything else in the protocol
+// strings are java's modified utf-8, with a u16 length
+use std::io::{self, Read, Write};
+use crate::de::{self, Deserialize, ByteReader, ErrorKind};
+use crate::ser::{Serialize, ByteWriter};
+use super::{Tag, TagType, Compound, MAX_DEPTH};
+
+fn read_type(input: &mut ByteReader<'_>) -> de::Result<TagType> {
+    let offset = input.offset();
+    let id = u8::deserialize(input)?;
+    TagType::from_id(id).ok_or_else(|| de::Error::new(ErrorKind::BadEnumTag, offset))
+}
+
+fn read_len(input: &mut ByteReader<'_>) -> de::Result<usize> {
+    use std::convert::TryFrom;
+
+    let len = i32::deserialize(input)?;
+    usize::try_from(len).map_err(|_| input.error(ErrorKind::BadLength))
+}
+
+// an array can't have more elements than there are bytes left, so that's as much as gets preallocated
+fn read_array<T: for<'de> Deserialize<'de, Value = T>>(input: &mut ByteReader<'_>) -> de::Result<Vec<T>> {
+    let len = read_len(input)?;
+    let mut values = Vec::with_capacity(std::cmp::min(len, input.remaining_len() / std::mem::size_of::<T>()));
+    for _ in 0..len {
+        values.push(T::deserialize(input)?);
+    }
+    Ok(values)
+}
+
+// decodes modified utf-8, which writes 0 as two bytes and everything outside
+// the basic multilingual plane as a surrogate pair of three bytes each
+fn decode_mutf8(bytes: &[u8]) -> Option<String> {
+    if bytes.iter().all(|byte| (1..0x80).contains(byte)) {
+        return std::str::from_utf8(bytes).ok().map(str::to_owned);
+    }
+
+    let mut units = Vec::with_capacity(bytes.len());
+    let mut rest = bytes;
+    while let Some((&first, tail)) = rest.split_first() {
+        let (unit, tail) = match (first, tail) {
+            (0x01..=0x7f, tail) => (first as u16, tail),
+            (0xc0..=0xdf, [second, tail @ ..]) if second & 0xc0 == 0x80 => {
+                (((first as u16 & 0x1f) << 6) | (*second as u16 & 0x3f), tail)
+            },
+            (0xe0..=0xef, [second, third, tail @ ..]) if second & 0xc0 == 0x80 && third & 0xc0 == 0x80 => {
+                (((first as u16 & 0x0f) << 12) | ((*second as u16 & 0x3f) << 6) | (*third as u16 & 0x3f), tail)
+            },
+            _ => return None,
+        };
+        units.push(unit);
+        rest = tail;
+    }
+    String::from_utf16(&units).ok()
+}
+
+fn encode_mutf8(string: &str, output: &mut Vec<u8>) {
+    for unit in string.encode_utf16() {
+        match unit {
+            0x01..=0x7f => output.push(unit as u8),
+            0 | 0x80..=0x7ff => output.extend_from_slice(&[0xc0 | (unit >> 6) as u8, 0x80 | (unit & 0x3f) as u8]),
+            _ => output.extend_from_slice(&[0xe0 | (unit >> 12) as u8, 0x80 | ((unit >> 6) & 0x3f) as u8, 0x80 | (unit & 0x3f) as u8]),
+        }
+    }
+}
+
+fn read_string(input: &mut ByteReader<'_>) -> de::Result<String> {
+    let len = u16::deserialize(input)? as usize;
+    let offset = input.offset();
+    let bytes = input.read_bytes(len)?;
+    decode_mutf8(bytes).ok_or_else(|| de::Error::new(ErrorKind::BadUtf8, offset))
+}
+
+fn write_string(string: &str, output: &mut ByteWriter<'_>) {
+    let mut bytes = Vec::with_capacity(string.len());
+    encode_mutf8(string, &mut bytes);
+    let len: u16 = std::convert::TryFrom::try_from(bytes.len()).expect("nbt strings can't be longer than 65535 bytes");
+    u16::serialize(&len, output);
+    output.write_bytes(&bytes);
+}
+
+fn read_compound(input: &mut ByteReader<'_>, depth: usize) -> de::Result<Compound> {
+    let mut compound = Compound::new();
+    loop {
+        let ty = read_type(input)?;
+        if ty == TagType::End {
+            return Ok(compound);
+        }
+        let name = read_string(input)?;
+        let tag = read_payload(ty, input, depth + 1)?;
+        compound.insert(name, tag);
+    }
+}
+
+// a list can claim far more elements than it has, and each tag takes a lot more memory
+// than the single byte it can be read from, so only this many get reserved up front
+const MAX_RESERVED_TAGS: usize = 1024;
+
+fn read_list(input: &mut ByteReader<'_>, depth: usize) -> de::Result<Vec<Tag>> {
+    let elem = read_type(input)?;
+    let len = read_len(input)?;
+    // empty lists can say their elements are end tags, but nothing else can
+    if elem == TagType::End && len > 0 {
+        return Err(input.error(ErrorKind::BadEnumTag));
+    }
+    let mut tags = Vec::with_capacity(len.min(input.remaining_len()).min(MAX_RESERVED_TAGS));
+    for _ in 0..len {
+        tags.push(read_payload(elem, input, depth + 1)?);
+    }
+    Ok(tags)
+}
+
+// everything that isn't a list or compound, kept out of read_payload so the recursion uses less stack
+fn read_scalar(ty: TagType, input: &mut ByteReader<'_>) -> de::Result<Tag> {
+    Ok(match ty {
+        TagType::Byte => Tag::Byte(i8::deserialize(input)?),
+        TagType::Short => Tag::Short(i16::deserialize(input)?),
+        TagType::Int => Tag::Int(i32::deserialize(input)?),
+        TagType::Long => Tag::Long(i64::deserialize(input)?),
+        TagType::Float => Tag::Float(f32::deserialize(input)?),
+        TagType::Double => Tag::Double(f64::deserialize(input)?),
+        TagType::ByteArray => Tag::ByteArray(read_array(input)?),
+        TagType::String => Tag::String(read_string(input)?),
+        TagType::IntArray => Tag::IntArray(read_array(input)?),
+        TagType::LongArray => Tag::LongArray(read_array(input)?),
+        TagType::End | TagType::List | TagType::Compound => return Err(input.error(ErrorKind::BadEnumTag)),
+    })
+}
+
+// depth is how many lists and compounds this is inside of
+fn read_payload(ty: TagType, input: &mut ByteReader<'_>, depth: usize) -> de::Result<Tag> {
+    if depth > MAX_DEPTH {
+        return Err(input.error(ErrorKind::TooDeep));
+    }
+    match ty {
+        TagType::List => read_list(input, depth).map(Tag::List),
+        TagType::Compound => read_compound(input, depth).map(Tag::Compound),
+        _ => read_scalar(ty, input),
+    }
+}
+
+fn write_array<T: Serialize<Value = T>>(values: &[T], output: &mut ByteWriter<'_>) {
+    let len: i32 = std::convert::TryFrom::try_from(values.len()).expect("too many elements for an nbt array");
+    i32::serialize(&len, output);
+    for value in values {
+        T::serialize(value, output);
+    }
+}
+
+fn write_compound(compound: &Compound, output: &mut ByteWriter<'_>) {
+    for (name, tag) in compound {
+        output.write_byte(tag.tag_type().id());
+        write_string(name, output);
+        write_payload(tag, output);
+    }
+    output.write_byte(TagType::End.id());
+}
+
+fn write_payload(tag: &Tag, output: &mut ByteWriter<'_>) {
+    match tag {
+        Tag::Byte(value) => i8::serialize(value, output),
+        Tag::Short(value) => i16::serialize(value, output),
+        Tag::Int(value) => i32::serialize(value, output),
+        Tag::Long(value) => i64::serialize(value, output),
+        Tag::Float(value) => f32::serialize(value, output),
+        Tag::Double(value) => f64::serialize(value, output),
+        Tag::ByteArray(values) => write_array(values, output),
+        Tag::String(value) => write_string(value, output),
+        Tag::List(tags) => {
+            let elem = tags.first().map_or(TagType::End, Tag::tag_type);
+            assert!(tags.iter().all(|tag| tag.tag_type() == elem), "nbt list elements have to all be the same type");
+            output.write_byte(elem.id());
+            let len: i32 = std::convert::TryFrom::try_from(tags.len()).expect("too many elements for an nbt list");
+            i32::serialize(&len, output);
+            for tag in tags {
+                write_payload(tag, output);
+            }
+        },
+        Tag::Compound(compound) => write_compound(compound, output),
+        Tag::IntArray(values) => write_array(values, output),
+        Tag::LongArray(values) => write_array(values, output),
+    }
+}
+
+impl Deserialize<'_> for Tag {
+    type Value = Self;
+
+    // a type id followed by the payload, without a name
+    fn deserialize(input: &mut ByteReader<'_>) -> de::Result<Self> {
+        let ty = read_type(input)?;
+        read_payload(ty, input, 0)
+    }
+}
+
+impl Serialize for Tag {
+    type Value = Self;
+
+    fn serialize(value: &Self, output: &mut ByteWriter<'_>) {
+        output.write_byte(value.tag_type().id());
+        write_payload(value, output);
+    }
+}
+
+fn read_root(input: &mut ByteReader<'_>) -> de::Result<()> {
+    let offset = input.offset();
+    match read_type(input)? {
+        TagType::Compound => Ok(()),
+        _ => Err(de::Error::new(ErrorKind::BadEnumTag, offset)),
+    }
+}
+
+// a whole nbt document, a compound with a name (which is usually empty)
+// this is the form files and packets before 1.20.2 use
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Nbt {
+    pub name: String,
+    pub root: Compound,
+}
+
+impl Nbt {
+    pub fn new(root: Compound) -> Self {
+        Self { name: String::new(), root }
+    }
+}
+
+impl Deserialize<'_> for Nbt {
+    type Value = Self;
+
+    fn deserialize(input: &mut ByteReader<'_>) -> de::Result<Self> {
+        read_root(input)?;
+        let name = read_string(input)?;
+        let root = read_compound(input, 0)?;
+        Ok(Self { name, root })
+    }
+}
+
+impl Serialize for Nbt {
+    type Value = Self;
+
+    fn serialize(value: &Self, output: &mut ByteWriter<'_>) {
+        output.write_byte(TagType::Compound.id());
+        write_string(&value.name, output);
+        write_compound(&value.root, output);
+    }
+}
+
+// the root compound without a name, which is what packets use since 1.20.2
+// for #[packet(with = "nbt::NetworkNbt")] on a Compound
+pub enum NetworkNbt {}
+
+impl Deserialize<'_> for NetworkNbt {
+    type Value = Compound;
+
+    fn deserialize(input: &mut ByteReader<'_>) -> de::Result<Compound> {
+        read_root(input)?;
+        read_compound(input, 0)
+    }
+}
+
+impl Serialize for NetworkNbt {
+    type Value = Compound;
+
+    fn serialize(value: &Compound, output: &mut ByteWriter<'_>) {
+        output.write_byte(TagType::Compound.id());
+        write_compound(value, output);
+    }
+}
+
+// nbt that can be left out by writing an end tag instead, like the nbt on an item
+// for #[packet(with = "nbt::OptionalNbt")] on an Option<Nbt>
+pub enum OptionalNbt {}
+
+impl Deserialize<'_> for OptionalNbt {
+    type Value = Option<Nbt>;
+
+    fn deserialize(input: &mut ByteReader<'_>) -> de::Result<Option<Nbt>> {
+        match input.peek_byte() {
+            Some(0) => {
+                input.read_byte()?;
+                Ok(None)
+            },
+            _ => Nbt::deserialize(input).map(Some),
+        }
+    }
+}
+
+impl Serialize for OptionalNbt {
+    type Value = Option<Nbt>;
+
+    fn serialize(value: &Option<Nbt>, output: &mut ByteWriter<'_>) {
+        match value {
+            Some(nbt) => Nbt::serialize(nbt, output),
+            None => output.write_byte(TagType::End.id()),
+        }
+    }
+}
+
+// nbt from a file, which is usually gzipped but can also be zlib compressed or not compressed at all
+pub fn from_compressed(bytes: &[u8]) -> io::Result<Nbt> {
+    let mut decompressed = Vec::new();
+    let bytes = match bytes {
+        [0x1f, 0x8b, ..] => {
+            flate2::read::GzDecoder::new(bytes).read_to_end(&mut decompressed)?;
+            &decompressed[..]
+        },
+        [0x78, ..] => {
+            flate2::read::ZlibDecoder::new(bytes).read_to_end(&mut decompressed)?;
+            &decompressed[..]
+        },
+        _ => bytes,
+    };
+    Ok(de::deserialize_all::<Nbt>(bytes)?)
+}
+
+fn uncompressed(nbt: &Nbt) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    Nbt::serialize(nbt, &mut ByteWriter::new(&mut bytes));
+    bytes
+}
+
+pub fn to_gzip(nbt: &Nbt) -> Vec<u8> {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(&uncompressed(nbt)).expect("writing to a Vec can't fail");
+    encoder.finish().expect("writing to a Vec can't fail")
+}
+
+pub fn to_zlib(nbt: &Nbt) -> Vec<u8> {
+    let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(&uncompressed(nbt)).expect("writing to a Vec can't fail");
+    encoder.finish().expect("writing to a Vec can't fail")
+}
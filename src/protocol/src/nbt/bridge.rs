@@ -0,0 +1,732 @@
+// serde for nbt, so plain serde types can be turned into tags and back
+// sequences are lists, maps and structs are compounds, a None is left out of its compound
+// and enums are a string or a compound with one entry, like serde_json does them
+use std::fmt::{self, Display, Formatter};
+use serde::{ser, de, Serialize, Deserialize};
+use serde::de::{DeserializeSeed, IntoDeserializer, Visitor};
+use super::{Tag, TagType, Compound, prefix_field, prefix_index};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SerdeError {
+    message: String,
+    // where in the tree it went wrong, like FromNbtError's
+    path: String,
+}
+
+impl SerdeError {
+    fn new(message: impl Display) -> Self {
+        Self { message: message.to_string(), path: String::new() }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn in_field(mut self, name: &str) -> Self {
+        prefix_field(&mut self.path, name);
+        self
+    }
+
+    fn in_index(mut self, index: usize) -> Self {
+        prefix_index(&mut self.path, index);
+        self
+    }
+}
+
+impl Display for SerdeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{} at `{}`", self.message, self.path)
+        }
+    }
+}
+
+impl std::error::Error for SerdeError {}
+
+impl ser::Error for SerdeError {
+    fn custom<T: Display>(message: T) -> Self {
+        Self::new(message)
+    }
+}
+
+impl de::Error for SerdeError {
+    fn custom<T: Display>(message: T) -> Self {
+        Self::new(message)
+    }
+}
+
+type Result<T> = std::result::Result<T, SerdeError>;
+
+// the newtype struct names the array types serialize as, so Serializer knows to make an array
+const BYTE_ARRAY: &str = "nbt::ByteArray";
+const INT_ARRAY: &str = "nbt::IntArray";
+const LONG_ARRAY: &str = "nbt::LongArray";
+
+macro_rules! array_type {
+    ($($name: ident($elem: ty) = $newtype: expr),*) => {$(
+        #[derive(Clone, Debug, Default, PartialEq, Eq)]
+        pub struct $name(pub Vec<$elem>);
+
+        impl Serialize for $name {
+            fn serialize<S: ser::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+                serializer.serialize_newtype_struct($newtype, &self.0)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+                Vec::deserialize(deserializer).map(Self)
+            }
+        }
+    )*};
+}
+
+// a Vec<i8>, Vec<i32> or Vec<i64> becomes a list, these become arrays instead
+// (like a chunk's heightmaps, which are long arrays)
+array_type!(ByteArray(i8) = BYTE_ARRAY, IntArray(i32) = INT_ARRAY, LongArray(i64) = LONG_ARRAY);
+
+pub fn to_tag<T: Serialize + ?Sized>(value: &T) -> Result<Tag> {
+    value.serialize(Serializer)?.ok_or_else(|| SerdeError::new("nbt has no null, a None can only be a field"))
+}
+
+pub fn to_compound<T: Serialize + ?Sized>(value: &T) -> Result<Compound> {
+    match to_tag(value)? {
+        Tag::Compound(compound) => Ok(compound),
+        tag => Err(SerdeError::new(format_args!("expected a compound but found {}", tag.tag_type()))),
+    }
+}
+
+pub fn from_tag<T: de::DeserializeOwned>(tag: Tag) -> Result<T> {
+    T::deserialize(tag)
+}
+
+pub fn from_compound<T: de::DeserializeOwned>(compound: Compound) -> Result<T> {
+    T::deserialize(Tag::Compound(compound))
+}
+
+// the elements of a list that's meant to be an array
+fn array_elements<T>(tags: Vec<Tag>, ty: TagType, elem: impl Fn(Tag) -> Option<T>) -> Result<Vec<T>> {
+    tags.into_iter().enumerate().map(|(i, tag)| {
+        let found = tag.tag_type();
+        elem(tag).ok_or_else(|| SerdeError::new(format_args!("expected {} but found {}", ty, found)).in_index(i))
+    }).collect()
+}
+
+fn variant(name: &str, tag: Tag) -> Option<Tag> {
+    let mut compound = Compound::new();
+    compound.insert(name.to_owned(), tag);
+    Some(Tag::Compound(compound))
+}
+
+// the result is None for a None, which compounds leave out and everything else rejects
+struct Serializer;
+
+impl ser::Serializer for Serializer {
+    type Ok = Option<Tag>;
+    type Error = SerdeError;
+    type SerializeSeq = SerializeList;
+    type SerializeTuple = SerializeList;
+    type SerializeTupleStruct = SerializeList;
+    type SerializeTupleVariant = SerializeVariant<SerializeList>;
+    type SerializeMap = SerializeCompound;
+    type SerializeStruct = SerializeCompound;
+    type SerializeStructVariant = SerializeVariant<SerializeCompound>;
+
+    fn serialize_bool(self, value: bool) -> Result<Option<Tag>> {
+        Ok(Some(value.into()))
+    }
+
+    fn serialize_i8(self, value: i8) -> Result<Option<Tag>> {
+        Ok(Some(value.into()))
+    }
+
+    fn serialize_i16(self, value: i16) -> Result<Option<Tag>> {
+        Ok(Some(value.into()))
+    }
+
+    fn serialize_i32(self, value: i32) -> Result<Option<Tag>> {
+        Ok(Some(value.into()))
+    }
+
+    fn serialize_i64(self, value: i64) -> Result<Option<Tag>> {
+        Ok(Some(value.into()))
+    }
+
+    // nbt has no unsigned types, so these go in the next bigger signed one
+    fn serialize_u8(self, value: u8) -> Result<Option<Tag>> {
+        Ok(Some(Tag::Short(value.into())))
+    }
+
+    fn serialize_u16(self, value: u16) -> Result<Option<Tag>> {
+        Ok(Some(Tag::Int(value.into())))
+    }
+
+    fn serialize_u32(self, value: u32) -> Result<Option<Tag>> {
+        Ok(Some(Tag::Long(value.into())))
+    }
+
+    fn serialize_u64(self, value: u64) -> Result<Option<Tag>> {
+        use std::convert::TryFrom;
+
+        i64::try_from(value)
+            .map(|value| Some(Tag::Long(value)))
+            .map_err(|_| SerdeError::new(format_args!("{} is too big for a long", value)))
+    }
+
+    fn serialize_f32(self, value: f32) -> Result<Option<Tag>> {
+        Ok(Some(value.into()))
+    }
+
+    fn serialize_f64(self, value: f64) -> Result<Option<Tag>> {
+        Ok(Some(value.into()))
+    }
+
+    fn serialize_char(self, value: char) -> Result<Option<Tag>> {
+        Ok(Some(Tag::String(value.to_string())))
+    }
+
+    fn serialize_str(self, value: &str) -> Result<Option<Tag>> {
+        Ok(Some(value.into()))
+    }
+
+    fn serialize_bytes(self, value: &[u8]) -> Result<Option<Tag>> {
+        Ok(Some(Tag::ByteArray(value.iter().map(|&byte| byte as i8).collect())))
+    }
+
+    fn serialize_none(self) -> Result<Option<Tag>> {
+        Ok(None)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Option<Tag>> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Option<Tag>> {
+        Ok(Some(Tag::Compound(Compound::new())))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Option<Tag>> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str) -> Result<Option<Tag>> {
+        Ok(Some(variant.into()))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, name: &'static str, value: &T) -> Result<Option<Tag>> {
+        match (name, value.serialize(self)?) {
+            (BYTE_ARRAY, Some(Tag::List(tags))) => array_elements(tags, TagType::Byte, |tag| match tag {
+                Tag::Byte(value) => Some(value),
+                _ => None,
+            }).map(|values| Some(Tag::ByteArray(values))),
+            (INT_ARRAY, Some(Tag::List(tags))) => array_elements(tags, TagType::Int, |tag| match tag {
+                Tag::Int(value) => Some(value),
+                _ => None,
+            }).map(|values| Some(Tag::IntArray(values))),
+            (LONG_ARRAY, Some(Tag::List(tags))) => array_elements(tags, TagType::Long, |tag| match tag {
+                Tag::Long(value) => Some(value),
+                _ => None,
+            }).map(|values| Some(Tag::LongArray(values))),
+            (_, tag) => Ok(tag),
+        }
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant_name: &'static str,
+        value: &T,
+    ) -> Result<Option<Tag>> {
+        let tag = to_tag(value).map_err(|err| err.in_field(variant_name))?;
+        Ok(variant(variant_name, tag))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeList> {
+        Ok(SerializeList { tags: Vec::with_capacity(len.unwrap_or(0)) })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeList> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<SerializeList> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeVariant<SerializeList>> {
+        Ok(SerializeVariant { variant, inner: self.serialize_seq(Some(len))? })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<SerializeCompound> {
+        Ok(SerializeCompound { compound: Compound::new(), key: None })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<SerializeCompound> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeVariant<SerializeCompound>> {
+        Ok(SerializeVariant { variant, inner: self.serialize_map(Some(len))? })
+    }
+}
+
+struct SerializeList {
+    tags: Vec<Tag>,
+}
+
+impl SerializeList {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        let i = self.tags.len();
+        let tag = to_tag(value).map_err(|err| err.in_index(i))?;
+        if let Some(first) = self.tags.first() {
+            if first.tag_type() != tag.tag_type() {
+                let message = format_args!("a list of {} can't have a {} in it", first.tag_type(), tag.tag_type());
+                return Err(SerdeError::new(message).in_index(i));
+            }
+        }
+        self.tags.push(tag);
+        Ok(())
+    }
+}
+
+impl ser::SerializeSeq for SerializeList {
+    type Ok = Option<Tag>;
+    type Error = SerdeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Option<Tag>> {
+        Ok(Some(Tag::List(self.tags)))
+    }
+}
+
+impl ser::SerializeTuple for SerializeList {
+    type Ok = Option<Tag>;
+    type Error = SerdeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Option<Tag>> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeList {
+    type Ok = Option<Tag>;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Option<Tag>> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+struct SerializeCompound {
+    compound: Compound,
+    // the key serialize_key got, waiting for serialize_value
+    key: Option<String>,
+}
+
+impl SerializeCompound {
+    fn insert<T: Serialize + ?Sized>(&mut self, key: String, value: &T) -> Result<()> {
+        if let Some(tag) = value.serialize(Serializer).map_err(|err| err.in_field(&key))? {
+            self.compound.insert(key, tag);
+        }
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for SerializeCompound {
+    type Ok = Option<Tag>;
+    type Error = SerdeError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+        match to_tag(key)? {
+            Tag::String(key) => {
+                self.key = Some(key);
+                Ok(())
+            },
+            tag => Err(SerdeError::new(format_args!("compound keys have to be strings, not {}", tag.tag_type()))),
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        let key = self.key.take().expect("serialize_value called before serialize_key");
+        self.insert(key, value)
+    }
+
+    fn end(self) -> Result<Option<Tag>> {
+        Ok(Some(Tag::Compound(self.compound)))
+    }
+}
+
+impl ser::SerializeStruct for SerializeCompound {
+    type Ok = Option<Tag>;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<()> {
+        self.insert(key.to_owned(), value)
+    }
+
+    fn end(self) -> Result<Option<Tag>> {
+        ser::SerializeMap::end(self)
+    }
+}
+
+// a tuple or struct variant, which ends up in a compound with the variant's name as the only key
+struct SerializeVariant<S> {
+    variant: &'static str,
+    inner: S,
+}
+
+impl ser::SerializeTupleVariant for SerializeVariant<SerializeList> {
+    type Ok = Option<Tag>;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        let name = self.variant;
+        self.inner.push(value).map_err(|err| err.in_field(name))
+    }
+
+    fn end(self) -> Result<Option<Tag>> {
+        Ok(variant(self.variant, Tag::List(self.inner.tags)))
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeVariant<SerializeCompound> {
+    type Ok = Option<Tag>;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<()> {
+        let name = self.variant;
+        self.inner.insert(key.to_owned(), value).map_err(|err| err.in_field(name))
+    }
+
+    fn end(self) -> Result<Option<Tag>> {
+        Ok(variant(self.variant, Tag::Compound(self.inner.compound)))
+    }
+}
+
+fn visit_list<'de, V: Visitor<'de>>(tags: impl Iterator<Item = Tag>, visitor: V) -> Result<V::Value> {
+    visitor.visit_seq(ListAccess { tags: tags.enumerate() })
+}
+
+// reads T from the tag, any tag can be deserialized like this
+// arrays are read as sequences, so they can go in a Vec as well as the array types
+impl<'de> de::Deserializer<'de> for Tag {
+    type Error = SerdeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            Self::Byte(value) => visitor.visit_i8(value),
+            Self::Short(value) => visitor.visit_i16(value),
+            Self::Int(value) => visitor.visit_i32(value),
+            Self::Long(value) => visitor.visit_i64(value),
+            Self::Float(value) => visitor.visit_f32(value),
+            Self::Double(value) => visitor.visit_f64(value),
+            Self::ByteArray(values) => visit_list(values.into_iter().map(Tag::Byte), visitor),
+            Self::String(value) => visitor.visit_string(value),
+            Self::List(tags) => visit_list(tags.into_iter(), visitor),
+            Self::Compound(compound) => visitor.visit_map(CompoundAccess { entries: compound.into_iter(), value: None }),
+            Self::IntArray(values) => visit_list(values.into_iter().map(Tag::Int), visitor),
+            Self::LongArray(values) => visit_list(values.into_iter().map(Tag::Long), visitor),
+        }
+    }
+
+    // bools are bytes
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            Self::Byte(value) => visitor.visit_bool(value != 0),
+            tag => tag.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            Self::ByteArray(values) => visitor.visit_byte_buf(values.into_iter().map(|value| value as u8).collect()),
+            tag => tag.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_bytes(visitor)
+    }
+
+    // a field that isn't there never gets this far, so the tag is always Some
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            Self::Compound(compound) if compound.is_empty() => visitor.visit_unit(),
+            tag => tag.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self {
+            Self::String(variant) => visitor.visit_enum(variant.into_deserializer()),
+            Self::Compound(compound) if compound.len() == 1 => {
+                let (variant, value) = compound.into_iter().next().expect("the compound has one entry");
+                visitor.visit_enum(VariantAccess { variant, value })
+            },
+            tag => Err(SerdeError::new(format_args!(
+                "expected a string or a compound with one entry for an enum but found {}",
+                tag.tag_type(),
+            ))),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, SerdeError> for Tag {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+struct ListAccess<I> {
+    tags: std::iter::Enumerate<I>,
+}
+
+impl<'de, I: Iterator<Item = Tag>> de::SeqAccess<'de> for ListAccess<I> {
+    type Error = SerdeError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        match self.tags.next() {
+            Some((i, tag)) => seed.deserialize(tag).map(Some).map_err(|err| err.in_index(i)),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.tags.size_hint().1
+    }
+}
+
+struct CompoundAccess {
+    entries: std::collections::btree_map::IntoIter<String, Tag>,
+    // the entry whose key was just read
+    value: Option<(String, Tag)>,
+}
+
+impl<'de> de::MapAccess<'de> for CompoundAccess {
+    type Error = SerdeError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        match self.entries.next() {
+            Some((key, value)) => {
+                let read = seed.deserialize(key.as_str().into_deserializer())?;
+                self.value = Some((key, value));
+                Ok(Some(read))
+            },
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        let (key, value) = self.value.take().expect("next_value called before next_key");
+        seed.deserialize(value).map_err(|err| err.in_field(&key))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+// an enum variant written as a compound, the variant's name is the key
+struct VariantAccess {
+    variant: String,
+    value: Tag,
+}
+
+impl<'de> de::EnumAccess<'de> for VariantAccess {
+    type Error = SerdeError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self)> {
+        let read = seed.deserialize(self.variant.as_str().into_deserializer())?;
+        Ok((read, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for VariantAccess {
+    type Error = SerdeError;
+
+    fn unit_variant(self) -> Result<()> {
+        let variant = self.variant;
+        <()>::deserialize(self.value).map_err(|err| err.in_field(&variant))
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        let variant = self.variant;
+        seed.deserialize(self.value).map_err(|err| err.in_field(&variant))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        let variant = self.variant;
+        de::Deserializer::deserialize_seq(self.value, visitor).map_err(|err| err.in_field(&variant))
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value> {
+        let variant = self.variant;
+        de::Deserializer::deserialize_map(self.value, visitor).map_err(|err| err.in_field(&variant))
+    }
+}
+
+// tags can go in serde types too, to keep part of the tree as it is
+impl Serialize for Tag {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        match self {
+            Self::Byte(value) => serializer.serialize_i8(*value),
+            Self::Short(value) => serializer.serialize_i16(*value),
+            Self::Int(value) => serializer.serialize_i32(*value),
+            Self::Long(value) => serializer.serialize_i64(*value),
+            Self::Float(value) => serializer.serialize_f32(*value),
+            Self::Double(value) => serializer.serialize_f64(*value),
+            Self::ByteArray(values) => serializer.serialize_newtype_struct(BYTE_ARRAY, values),
+            Self::String(value) => serializer.serialize_str(value),
+            Self::List(tags) => tags.serialize(serializer),
+            Self::Compound(compound) => compound.serialize(serializer),
+            Self::IntArray(values) => serializer.serialize_newtype_struct(INT_ARRAY, values),
+            Self::LongArray(values) => serializer.serialize_newtype_struct(LONG_ARRAY, values),
+        }
+    }
+}
+
+// a visitor can't tell an array from a list, so arrays come back as lists
+impl<'de> Deserialize<'de> for Tag {
+    fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        deserializer.deserialize_any(TagVisitor)
+    }
+}
+
+struct TagVisitor;
+
+impl<'de> Visitor<'de> for TagVisitor {
+    type Value = Tag;
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "an nbt tag")
+    }
+
+    fn visit_bool<E: de::Error>(self, value: bool) -> std::result::Result<Tag, E> {
+        Ok(value.into())
+    }
+
+    fn visit_i8<E: de::Error>(self, value: i8) -> std::result::Result<Tag, E> {
+        Ok(value.into())
+    }
+
+    fn visit_i16<E: de::Error>(self, value: i16) -> std::result::Result<Tag, E> {
+        Ok(value.into())
+    }
+
+    fn visit_i32<E: de::Error>(self, value: i32) -> std::result::Result<Tag, E> {
+        Ok(value.into())
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> std::result::Result<Tag, E> {
+        Ok(value.into())
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> std::result::Result<Tag, E> {
+        use std::convert::TryFrom;
+
+        i64::try_from(value)
+            .map(Tag::Long)
+            .map_err(|_| E::invalid_value(de::Unexpected::Unsigned(value), &self))
+    }
+
+    fn visit_f32<E: de::Error>(self, value: f32) -> std::result::Result<Tag, E> {
+        Ok(value.into())
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> std::result::Result<Tag, E> {
+        Ok(value.into())
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> std::result::Result<Tag, E> {
+        Ok(value.into())
+    }
+
+    fn visit_string<E: de::Error>(self, value: String) -> std::result::Result<Tag, E> {
+        Ok(value.into())
+    }
+
+    fn visit_bytes<E: de::Error>(self, value: &[u8]) -> std::result::Result<Tag, E> {
+        Ok(Tag::ByteArray(value.iter().map(|&byte| byte as i8).collect()))
+    }
+
+    fn visit_some<D: de::Deserializer<'de>>(self, deserializer: D) -> std::result::Result<Tag, D::Error> {
+        Tag::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<Tag, A::Error> {
+        let mut tags = Vec::<Tag>::new();
+        while let Some(tag) = seq.next_element::<Tag>()? {
+            if let Some(first) = tags.first() {
+                if first.tag_type() != tag.tag_type() {
+                    let message = format_args!("a list of {} can't have a {} in it", first.tag_type(), tag.tag_type());
+                    return Err(de::Error::custom(message));
+                }
+            }
+            tags.push(tag);
+        }
+        Ok(Tag::List(tags))
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> std::result::Result<Tag, A::Error> {
+        let mut compound = Compound::new();
+        while let Some((key, tag)) = map.next_entry::<String, Tag>()? {
+            compound.insert(key, tag);
+        }
+        Ok(Tag::Compound(compound))
+    }
+}
@@ -0,0 +1,280 @@
+// named binary tag, what minecraft uses for structured data like the dimension codec,
+// chunk heightmaps, items and block entities
+// the binary form is in binary.rs, snbt (the text form commands use) in snbt.rs
+// and serde support in bridge.rs
+use std::collections::{BTreeMap, HashMap};
+
+mod binary;
+mod snbt;
+mod bridge;
+
+pub use binary::{Nbt, NetworkNbt, OptionalNbt, from_compressed, to_gzip, to_zlib};
+pub use snbt::SnbtError;
+pub use bridge::{SerdeError, ByteArray, IntArray, LongArray, to_tag, to_compound, from_tag, from_compound};
+
+// vanilla won't read anything nested deeper than this, so neither does this
+pub const MAX_DEPTH: usize = 512;
+
+pub type Compound = BTreeMap<String, Tag>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TagType {
+    End,
+    Byte,
+    Short,
+    Int,
+    Long,
+    Float,
+    Double,
+    ByteArray,
+    String,
+    List,
+    Compound,
+    IntArray,
+    LongArray,
+}
+
+impl TagType {
+    const ALL: [TagType; 13] = [
+        Self::End, Self::Byte, Self::Short, Self::Int, Self::Long, Self::Float, Self::Double,
+        Self::ByteArray, Self::String, Self::List, Self::Compound, Self::IntArray, Self::LongArray,
+    ];
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        Self::ALL.get(id as usize).copied()
+    }
+
+    pub fn id(self) -> u8 {
+        self as u8
+    }
+}
+
+impl std::fmt::Display for TagType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::End => "end",
+            Self::Byte => "byte",
+            Self::Short => "short",
+            Self::Int => "int",
+            Self::Long => "long",
+            Self::Float => "float",
+            Self::Double => "double",
+            Self::ByteArray => "byte array",
+            Self::String => "string",
+            Self::List => "list",
+            Self::Compound => "compound",
+            Self::IntArray => "int array",
+            Self::LongArray => "long array",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// lists have to have elements of the same type, writing one that doesn't panics
+#[derive(Clone, Debug, PartialEq)]
+pub enum Tag {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<i8>),
+    String(String),
+    List(Vec<Tag>),
+    Compound(Compound),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+impl Tag {
+    pub fn tag_type(&self) -> TagType {
+        match self {
+            Self::Byte(_) => TagType::Byte,
+            Self::Short(_) => TagType::Short,
+            Self::Int(_) => TagType::Int,
+            Self::Long(_) => TagType::Long,
+            Self::Float(_) => TagType::Float,
+            Self::Double(_) => TagType::Double,
+            Self::ByteArray(_) => TagType::ByteArray,
+            Self::String(_) => TagType::String,
+            Self::List(_) => TagType::List,
+            Self::Compound(_) => TagType::Compound,
+            Self::IntArray(_) => TagType::IntArray,
+            Self::LongArray(_) => TagType::LongArray,
+        }
+    }
+}
+
+macro_rules! impl_from_for_tag {
+    ($($t: ty => $variant: ident),*) => {
+        $(impl From<$t> for Tag {
+            fn from(value: $t) -> Self {
+                Self::$variant(value)
+            }
+        })*
+    };
+}
+
+impl_from_for_tag!(
+    i8 => Byte, i16 => Short, i32 => Int, i64 => Long, f32 => Float, f64 => Double,
+    Vec<i8> => ByteArray, String => String, Vec<Tag> => List, Compound => Compound,
+    Vec<i32> => IntArray, Vec<i64> => LongArray
+);
+
+impl From<bool> for Tag {
+    fn from(value: bool) -> Self {
+        Self::Byte(value as i8)
+    }
+}
+
+impl From<&str> for Tag {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_owned())
+    }
+}
+
+// a tag that isn't what FromNbt expected, path is where in the tree it is (e.g. `biomes[2].name`)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FromNbtError {
+    WrongType { expected: TagType, found: TagType, path: String },
+    Missing { path: String },
+}
+
+// paths are written like `biomes[2].name`, and built from the inside out
+fn prefix_field(path: &mut String, name: &str) {
+    *path = if path.is_empty() || path.starts_with('[') {
+        format!("{}{}", name, path)
+    } else {
+        format!("{}.{}", name, path)
+    };
+}
+
+fn prefix_index(path: &mut String, index: usize) {
+    *path = if path.is_empty() || path.starts_with('[') {
+        format!("[{}]{}", index, path)
+    } else {
+        format!("[{}].{}", index, path)
+    };
+}
+
+impl FromNbtError {
+    fn path_mut(&mut self) -> &mut String {
+        match self {
+            Self::WrongType { path, .. } | Self::Missing { path } => path,
+        }
+    }
+
+    pub fn in_field(mut self, name: &str) -> Self {
+        prefix_field(self.path_mut(), name);
+        self
+    }
+
+    pub fn in_index(mut self, index: usize) -> Self {
+        prefix_index(self.path_mut(), index);
+        self
+    }
+}
+
+impl std::fmt::Display for FromNbtError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::WrongType { expected, found, path } => write!(f, "expected {} but found {} at `{}`", expected, found, path),
+            Self::Missing { path } => write!(f, "`{}` is missing", path),
+        }
+    }
+}
+
+impl std::error::Error for FromNbtError {}
+
+// reading nbt into a typed value, implemented by hand for structs using get and get_optional
+pub trait FromNbt: Sized {
+    fn from_nbt(tag: &Tag) -> Result<Self, FromNbtError>;
+}
+
+fn wrong_type<T>(expected: TagType, tag: &Tag) -> Result<T, FromNbtError> {
+    Err(FromNbtError::WrongType { expected, found: tag.tag_type(), path: String::new() })
+}
+
+// the field called name from a compound
+pub fn get<T: FromNbt>(compound: &Compound, name: &str) -> Result<T, FromNbtError> {
+    match compound.get(name) {
+        Some(tag) => T::from_nbt(tag).map_err(|err| err.in_field(name)),
+        None => Err(FromNbtError::Missing { path: name.to_owned() }),
+    }
+}
+
+// like get, but it's fine for it to not be there
+pub fn get_optional<T: FromNbt>(compound: &Compound, name: &str) -> Result<Option<T>, FromNbtError> {
+    match compound.get(name) {
+        Some(tag) => T::from_nbt(tag).map(Some).map_err(|err| err.in_field(name)),
+        None => Ok(None),
+    }
+}
+
+macro_rules! impl_from_nbt {
+    ($($t: ty => $variant: ident),*) => {
+        $(impl FromNbt for $t {
+            fn from_nbt(tag: &Tag) -> Result<Self, FromNbtError> {
+                match tag {
+                    Tag::$variant(value) => Ok(value.clone()),
+                    _ => wrong_type(TagType::$variant, tag),
+                }
+            }
+        })*
+    };
+}
+
+impl_from_nbt!(i8 => Byte, i16 => Short, i32 => Int, i64 => Long, f32 => Float, f64 => Double, String => String);
+
+impl FromNbt for bool {
+    fn from_nbt(tag: &Tag) -> Result<Self, FromNbtError> {
+        i8::from_nbt(tag).map(|value| value != 0)
+    }
+}
+
+impl FromNbt for Tag {
+    fn from_nbt(tag: &Tag) -> Result<Self, FromNbtError> {
+        Ok(tag.clone())
+    }
+}
+
+// the elements of an array, as if they were a list of tags
+fn convert_array<T: FromNbt, V: Copy + Into<Tag>>(values: &[V]) -> Result<Vec<T>, FromNbtError> {
+    values.iter().enumerate()
+        .map(|(i, value)| T::from_nbt(&(*value).into()).map_err(|err| err.in_index(i)))
+        .collect()
+}
+
+// from a list, or from an array if T is the array's element type
+impl<T: FromNbt> FromNbt for Vec<T> {
+    fn from_nbt(tag: &Tag) -> Result<Self, FromNbtError> {
+        match tag {
+            Tag::List(tags) => tags.iter().enumerate()
+                .map(|(i, tag)| T::from_nbt(tag).map_err(|err| err.in_index(i)))
+                .collect(),
+            Tag::ByteArray(values) => convert_array(values),
+            Tag::IntArray(values) => convert_array(values),
+            Tag::LongArray(values) => convert_array(values),
+            _ => wrong_type(TagType::List, tag),
+        }
+    }
+}
+
+impl<T: FromNbt> FromNbt for BTreeMap<String, T> {
+    fn from_nbt(tag: &Tag) -> Result<Self, FromNbtError> {
+        match tag {
+            Tag::Compound(compound) => compound.keys().map(|name| Ok((name.clone(), get(compound, name)?))).collect(),
+            _ => wrong_type(TagType::Compound, tag),
+        }
+    }
+}
+
+impl<T: FromNbt> FromNbt for HashMap<String, T> {
+    fn from_nbt(tag: &Tag) -> Result<Self, FromNbtError> {
+        match tag {
+            Tag::Compound(compound) => compound.keys().map(|name| Ok((name.clone(), get(compound, name)?))).collect(),
+            _ => wrong_type(TagType::Compound, tag),
+        }
+    }
+}
@@ -0,0 +1,378 @@
+// stringified nbt, like {name:"jeb_",pos:[I;1,64,-3],health:20.0f}
+// Display prints it and FromStr parses it, in the same syntax vanilla's commands use
+use std::fmt::{self, Display, Formatter, Write};
+use super::{Tag, TagType, Compound, Nbt, MAX_DEPTH};
+
+// characters that can be in a key or string without quoting it
+fn is_unquoted(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '+')
+}
+
+fn write_quoted(f: &mut Formatter<'_>, string: &str) -> fmt::Result {
+    f.write_char('"')?;
+    for c in string.chars() {
+        if c == '"' || c == '\\' {
+            f.write_char('\\')?;
+        }
+        f.write_char(c)?;
+    }
+    f.write_char('"')
+}
+
+fn write_array<T: Display>(f: &mut Formatter<'_>, prefix: char, values: &[T], suffix: &str) -> fmt::Result {
+    write!(f, "[{};", prefix)?;
+    for (i, value) in values.iter().enumerate() {
+        if i > 0 {
+            f.write_char(',')?;
+        }
+        write!(f, "{}{}", value, suffix)?;
+    }
+    f.write_char(']')
+}
+
+fn write_compound(f: &mut Formatter<'_>, compound: &Compound) -> fmt::Result {
+    f.write_char('{')?;
+    for (i, (name, tag)) in compound.iter().enumerate() {
+        if i > 0 {
+            f.write_char(',')?;
+        }
+        if !name.is_empty() && name.chars().all(is_unquoted) {
+            f.write_str(name)?;
+        } else {
+            write_quoted(f, name)?;
+        }
+        write!(f, ":{}", tag)?;
+    }
+    f.write_char('}')
+}
+
+// floats get printed with a decimal point so they don't read back as an int
+fn write_float<T: Display>(f: &mut Formatter<'_>, value: T, suffix: char) -> fmt::Result {
+    let value = value.to_string();
+    if value.contains(|c: char| c == '.' || c == 'e' || !c.is_ascii_digit() && c != '-') {
+        write!(f, "{}{}", value, suffix)
+    } else {
+        write!(f, "{}.0{}", value, suffix)
+    }
+}
+
+impl Display for Tag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Byte(value) => write!(f, "{}b", value),
+            Self::Short(value) => write!(f, "{}s", value),
+            Self::Int(value) => write!(f, "{}", value),
+            Self::Long(value) => write!(f, "{}L", value),
+            Self::Float(value) => write_float(f, value, 'f'),
+            Self::Double(value) => write_float(f, value, 'd'),
+            Self::ByteArray(values) => write_array(f, 'B', values, "b"),
+            Self::String(value) => write_quoted(f, value),
+            Self::List(tags) => {
+                f.write_char('[')?;
+                for (i, tag) in tags.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write!(f, "{}", tag)?;
+                }
+                f.write_char(']')
+            },
+            Self::Compound(compound) => write_compound(f, compound),
+            Self::IntArray(values) => write_array(f, 'I', values, ""),
+            Self::LongArray(values) => write_array(f, 'L', values, "L"),
+        }
+    }
+}
+
+// the root compound, the name isn't part of snbt
+impl Display for Nbt {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_compound(f, &self.root)
+    }
+}
+
+// position is in bytes from the start of the input
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SnbtError {
+    pub message: &'static str,
+    pub position: usize,
+}
+
+impl Display for SnbtError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for SnbtError {}
+
+type Result<T> = std::result::Result<T, SnbtError>;
+
+struct Parser<'a> {
+    input: &'a str,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error<T>(&self, message: &'static str) -> Result<T> {
+        Err(SnbtError { message, position: self.position })
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.position..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    // the next non-whitespace character, without taking it
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.rest().chars().next()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.position += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char, message: &'static str) -> Result<()> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            self.error(message)
+        }
+    }
+
+    fn unquoted(&mut self) -> &'a str {
+        self.skip_whitespace();
+        let rest = self.rest();
+        let len = rest.find(|c| !is_unquoted(c)).unwrap_or(rest.len());
+        self.position += len;
+        &rest[..len]
+    }
+
+    fn quoted(&mut self, quote: char) -> Result<String> {
+        // the opening quote
+        self.position += 1;
+        let mut string = String::new();
+        let mut chars = self.rest().char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some((_, escaped)) if escaped == quote || escaped == '\\' => string.push(escaped),
+                    _ => {
+                        self.position += i;
+                        return self.error("invalid escape");
+                    },
+                },
+                c if c == quote => {
+                    self.position += i + 1;
+                    return Ok(string);
+                },
+                c => string.push(c),
+            }
+        }
+        self.position = self.input.len();
+        self.error("unterminated string")
+    }
+
+    fn key(&mut self) -> Result<String> {
+        match self.peek() {
+            Some(quote @ ('"' | '\'')) => self.quoted(quote),
+            _ => match self.unquoted() {
+                "" => self.error("expected a key"),
+                key => Ok(key.to_owned()),
+            },
+        }
+    }
+
+    // depth is how many lists and compounds this is inside of
+    fn value(&mut self, depth: usize) -> Result<Tag> {
+        if depth > MAX_DEPTH {
+            return self.error("nested too deep");
+        }
+        match self.peek() {
+            Some('{') => self.compound(depth).map(Tag::Compound),
+            Some('[') => self.list(depth),
+            Some(quote @ ('"' | '\'')) => self.quoted(quote).map(Tag::String),
+            _ => {
+                let start = self.position;
+                match self.unquoted() {
+                    "" => {
+                        self.position = start;
+                        self.error("expected a value")
+                    },
+                    token => Ok(scalar(token)),
+                }
+            },
+        }
+    }
+
+    fn compound(&mut self, depth: usize) -> Result<Compound> {
+        self.expect('{', "expected `{`")?;
+        let mut compound = Compound::new();
+        if self.eat('}') {
+            return Ok(compound);
+        }
+        loop {
+            let key = self.key()?;
+            self.expect(':', "expected `:`")?;
+            let value = self.value(depth + 1)?;
+            compound.insert(key, value);
+            if self.eat('}') {
+                return Ok(compound);
+            }
+            self.expect(',', "expected `,` or `}`")?;
+        }
+    }
+
+    // the elements of a list or array, up to and including the closing ]
+    fn elements(&mut self, depth: usize) -> Result<Vec<Tag>> {
+        let mut tags = Vec::new();
+        if self.eat(']') {
+            return Ok(tags);
+        }
+        loop {
+            let position = self.position;
+            let tag = self.value(depth + 1)?;
+            if tags.first().is_some_and(|first| first.tag_type() != tag.tag_type()) {
+                self.position = position;
+                return self.error("list elements have to all be the same type");
+            }
+            tags.push(tag);
+            if self.eat(']') {
+                return Ok(tags);
+            }
+            self.expect(',', "expected `,` or `]`")?;
+        }
+    }
+
+    fn list(&mut self, depth: usize) -> Result<Tag> {
+        self.expect('[', "expected `[`")?;
+        // arrays start with their type, like [I;1,2,3]
+        let rest = self.rest().trim_start();
+        let array = match rest.as_bytes() {
+            [b'B', b';', ..] => Some(TagType::Byte),
+            [b'I', b';', ..] => Some(TagType::Int),
+            [b'L', b';', ..] => Some(TagType::Long),
+            _ => None,
+        };
+        let elem = match array {
+            Some(elem) => {
+                self.position = self.input.len() - rest.len() + 2;
+                elem
+            },
+            None => return self.elements(depth).map(Tag::List),
+        };
+
+        let position = self.position;
+        let tags = self.elements(depth)?;
+        if tags.iter().any(|tag| tag.tag_type() != elem) {
+            self.position = position;
+            return self.error("array elements have to match the array's type");
+        }
+        let values = tags.into_iter();
+        Ok(match elem {
+            TagType::Byte => Tag::ByteArray(values.map(|tag| match tag { Tag::Byte(value) => value, _ => unreachable!() }).collect()),
+            TagType::Int => Tag::IntArray(values.map(|tag| match tag { Tag::Int(value) => value, _ => unreachable!() }).collect()),
+            _ => Tag::LongArray(values.map(|tag| match tag { Tag::Long(value) => value, _ => unreachable!() }).collect()),
+        })
+    }
+}
+
+fn strip_sign(token: &str) -> &str {
+    token.strip_prefix(|c| matches!(c, '-' | '+')).unwrap_or(token)
+}
+
+fn all_digits(token: &str) -> bool {
+    token.bytes().all(|c| c.is_ascii_digit())
+}
+
+// 0 or digits without a leading zero, with an optional sign
+fn is_integer(token: &str) -> bool {
+    match strip_sign(token).as_bytes() {
+        [b'0'] => true,
+        [b'1'..=b'9', rest @ ..] => rest.iter().all(u8::is_ascii_digit),
+        _ => false,
+    }
+}
+
+// digits with a decimal point and an optional exponent, the point can only be left out
+// when there's a suffix, so a plain 1e5 is a string, as are nan and infinity
+fn is_decimal(token: &str, suffixed: bool) -> bool {
+    let token = strip_sign(token);
+    let (mantissa, exponent) = match token.find(['e', 'E']) {
+        Some(at) => (&token[..at], Some(&token[at + 1..])),
+        None => (token, None),
+    };
+    if let Some(exponent) = exponent {
+        let exponent = strip_sign(exponent);
+        if exponent.is_empty() || !all_digits(exponent) {
+            return false;
+        }
+    }
+    match mantissa.split_once('.') {
+        Some((whole, fraction)) => all_digits(whole) && all_digits(fraction) && !(whole.is_empty() && fraction.is_empty()),
+        None => suffixed && !mantissa.is_empty() && all_digits(mantissa),
+    }
+}
+
+// a number with an optional type suffix, true or false, or otherwise a string
+// numbers that don't fit their type are strings too, like vanilla does
+fn scalar(token: &str) -> Tag {
+    let looks_numeric = token.starts_with(|c: char| c.is_ascii_digit() || matches!(c, '-' | '+' | '.'));
+    if looks_numeric {
+        let (number, suffix) = token.split_at(token.len() - 1);
+        let integer = is_integer(number);
+        let decimal = is_decimal(number, true);
+        let parsed = match suffix {
+            "b" | "B" if integer => number.parse().ok().map(Tag::Byte),
+            "s" | "S" if integer => number.parse().ok().map(Tag::Short),
+            "l" | "L" if integer => number.parse().ok().map(Tag::Long),
+            "f" | "F" if decimal => number.parse().ok().map(Tag::Float),
+            "d" | "D" if decimal => number.parse().ok().map(Tag::Double),
+            _ if is_integer(token) => token.parse().ok().map(Tag::Int),
+            _ if is_decimal(token, false) => token.parse().ok().map(Tag::Double),
+            _ => None,
+        };
+        if let Some(tag) = parsed {
+            return tag;
+        }
+    }
+    match token {
+        "true" => Tag::Byte(1),
+        "false" => Tag::Byte(0),
+        _ => Tag::String(token.to_owned()),
+    }
+}
+
+impl std::str::FromStr for Tag {
+    type Err = SnbtError;
+
+    fn from_str(input: &str) -> Result<Self> {
+        let mut parser = Parser { input, position: 0 };
+        let tag = parser.value(0)?;
+        if parser.peek().is_some() {
+            return parser.error("expected the end of the input");
+        }
+        Ok(tag)
+    }
+}
+
+impl std::str::FromStr for Nbt {
+    type Err = SnbtError;
+
+    fn from_str(input: &str) -> Result<Self> {
+        match input.parse()? {
+            Tag::Compound(root) => Ok(Self::new(root)),
+            _ => Err(SnbtError { message: "expected a compound", position: 0 }),
+        }
+    }
+}
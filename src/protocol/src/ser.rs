@@ -0,0 +1,127 @@
+use crate::varnum::{VarNumReader, VarInt};
+
+pub struct ByteWriter<'a> {
+    output: &'a mut Vec<u8>
+}
+
+impl<'a> ByteWriter<'a> {
+    pub fn new(output: &'a mut Vec<u8>) -> Self {
+        Self { output }
+    }
+
+    pub fn write_byte(&mut self, byte: u8) {
+        self.output.push(byte);
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.output.extend_from_slice(bytes);
+    }
+
+    pub fn written_len(&self) -> usize {
+        self.output.len()
+    }
+}
+
+impl std::io::Write for ByteWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.write_bytes(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+// mirrors de::Deserialize, Value is what actually gets written
+// so that VarInt can write an i32, for example
+pub trait Serialize {
+    type Value: ?Sized;
+
+    fn serialize(value: &Self::Value, output: &mut ByteWriter<'_>);
+}
+
+// mirrors de::DeserializeVariant, serialize_variant writes everything but the tag
+pub trait SerializeVariant {
+    type Tag;
+
+    fn tag(value: &Self) -> Self::Tag;
+    fn serialize_variant(value: &Self, output: &mut ByteWriter<'_>);
+}
+
+// everything is big-endian
+macro_rules! impl_serialize_num {
+    ($t: ty, $size: literal) => {
+        impl Serialize for $t {
+            type Value = Self;
+
+            fn serialize(value: &Self::Value, output: &mut ByteWriter<'_>) {
+                let bytes: [u8; $size] = value.to_be_bytes();
+                output.write_bytes(&bytes);
+            }
+        }
+    };
+}
+
+impl_serialize_num!(u8, 1);
+impl_serialize_num!(i8, 1);
+impl_serialize_num!(u16, 2);
+impl_serialize_num!(i16, 2);
+impl_serialize_num!(u32, 4);
+impl_serialize_num!(i32, 4);
+impl_serialize_num!(u64, 8);
+impl_serialize_num!(i64, 8);
+impl_serialize_num!(u128, 16);
+impl_serialize_num!(f32, 4);
+impl_serialize_num!(f64, 8);
+
+impl Serialize for bool {
+    type Value = Self;
+
+    fn serialize(value: &Self::Value, output: &mut ByteWriter<'_>) {
+        output.write_byte(*value as u8);
+    }
+}
+
+impl<T: num::PrimInt> Serialize for VarNumReader<T> {
+    type Value = T;
+
+    fn serialize(value: &Self::Value, output: &mut ByteWriter<'_>) {
+        Self::write_to(*value, output.output);
+    }
+}
+
+impl<T: Serialize, const N: usize> Serialize for [T; N] where T::Value: Sized {
+    type Value = [T::Value; N];
+
+    fn serialize(value: &Self::Value, output: &mut ByteWriter<'_>) {
+        for val in value {
+            T::serialize(val, output);
+        }
+    }
+}
+
+impl Serialize for str {
+    type Value = Self;
+
+    fn serialize(value: &Self::Value, output: &mut ByteWriter<'_>) {
+        VarInt::serialize(&(value.len() as i32), output);
+        output.write_bytes(value.as_bytes());
+    }
+}
+
+impl<'a> Serialize for &'a str {
+    type Value = &'a str;
+
+    fn serialize(value: &Self::Value, output: &mut ByteWriter<'_>) {
+        <str as Serialize>::serialize(value, output);
+    }
+}
+
+impl Serialize for String {
+    type Value = Self;
+
+    fn serialize(value: &Self::Value, output: &mut ByteWriter<'_>) {
+        <str as Serialize>::serialize(value, output);
+    }
+}
@@ -0,0 +1,111 @@
+// serde types as packet fields
+// the value gets encoded when it's made, so a type that can't be written is an error then,
+// instead of when the packet gets written
+use std::ops::Deref;
+use crate::de::{self, Deserialize, ByteReader, ErrorKind};
+use crate::ser::{Serialize, ByteWriter};
+use crate::nbt;
+
+// a json string, like a chat message
+// T can borrow from the packet, the same as a &'de str can
+#[derive(Clone, Debug, PartialEq)]
+pub struct Json<T> {
+    value: T,
+    json: String,
+}
+
+impl<T: serde::Serialize> Json<T> {
+    pub fn new(value: T) -> serde_json::Result<Self> {
+        let json = serde_json::to_string(&value)?;
+        Ok(Self { value, json })
+    }
+}
+
+impl<T> Json<T> {
+    pub fn as_json(&self) -> &str {
+        &self.json
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T> Deref for Json<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<'de, T: serde::Deserialize<'de>> Deserialize<'de> for Json<T> {
+    type Value = Self;
+
+    fn deserialize(input: &mut ByteReader<'de>) -> de::Result<Self> {
+        let offset = input.offset();
+        let json = <&str>::deserialize(input)?;
+        let value = serde_json::from_str(json).map_err(|err| de::Error::new(ErrorKind::BadJson, offset).with_detail(err))?;
+        Ok(Self { value, json: json.to_owned() })
+    }
+}
+
+impl<T> Serialize for Json<T> {
+    type Value = Self;
+
+    fn serialize(value: &Self, output: &mut ByteWriter<'_>) {
+        str::serialize(&value.json, output);
+    }
+}
+
+// nbt with a named root compound, like the dimension codec in join game
+#[derive(Clone, Debug, PartialEq)]
+pub struct Nbt<T> {
+    value: T,
+    nbt: nbt::Nbt,
+}
+
+impl<T: serde::Serialize> Nbt<T> {
+    // T has to turn into a compound
+    pub fn new(value: T) -> Result<Self, nbt::SerdeError> {
+        let root = nbt::to_compound(&value)?;
+        Ok(Self { value, nbt: nbt::Nbt::new(root) })
+    }
+}
+
+impl<T> Nbt<T> {
+    pub fn as_nbt(&self) -> &nbt::Nbt {
+        &self.nbt
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T> Deref for Nbt<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T: serde::de::DeserializeOwned> Deserialize<'_> for Nbt<T> {
+    type Value = Self;
+
+    fn deserialize(input: &mut ByteReader<'_>) -> de::Result<Self> {
+        let offset = input.offset();
+        let nbt = nbt::Nbt::deserialize(input)?;
+        let value = nbt::from_compound(nbt.root.clone()).map_err(|err| de::Error::new(ErrorKind::BadNbt, offset).with_detail(err))?;
+        Ok(Self { value, nbt })
+    }
+}
+
+impl<T> Serialize for Nbt<T> {
+    type Value = Self;
+
+    fn serialize(value: &Self, output: &mut ByteWriter<'_>) {
+        nbt::Nbt::serialize(&value.nbt, output);
+    }
+}
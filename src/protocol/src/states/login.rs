@@ -0,0 +1,137 @@
+use std::convert::{TryFrom, TryInto};
+use tokio::io;
+use md5::{Md5, Digest};
+use crate::auth::Authenticator;
+use crate::chat::TextComponent;
+use crate::connection::invalid_data;
+use crate::encryption::{ServerKey, server_id_hash};
+use crate::packets::{serverbound, clientbound};
+use crate::states::{self, Conn, Play, Kick};
+use crate::types::Uuid;
+use crate::versions;
+
+pub struct OnlineMode {
+    pub key: ServerKey,
+    pub authenticator: Box<dyn Authenticator>,
+}
+
+pub struct LoginConfig {
+    // packets with at least this many bytes get compressed, None turns compression off
+    pub compression_threshold: Option<usize>,
+    // None means offline mode, where anyone can log in as anyone
+    pub online_mode: Option<OnlineMode>,
+}
+
+impl Default for LoginConfig {
+    fn default() -> Self {
+        Self {
+            compression_threshold: Some(256),
+            online_mode: None,
+        }
+    }
+}
+
+// skins and capes, signed by the session server
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct ProfileProperty {
+    pub name: String,
+    pub value: String,
+    pub signature: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Profile {
+    pub uuid: Uuid,
+    pub username: String,
+    pub properties: Vec<ProfileProperty>,
+}
+
+// the uuid vanilla gives players in offline mode
+// it's a version 3 uuid of "OfflinePlayer:<username>"
+pub fn offline_uuid(username: &str) -> Uuid {
+    let mut hash: [u8; 16] = Md5::digest(format!("OfflinePlayer:{}", username)).into();
+    hash[6] = (hash[6] & 0x0f) | 0x30;
+    hash[8] = (hash[8] & 0x3f) | 0x80;
+    Uuid(u128::from_be_bytes(hash))
+}
+
+fn valid_username(username: &str) -> bool {
+    !username.is_empty()
+        && username.len() <= 16
+        && username.bytes().all(|c| c.is_ascii_alphanumeric() || c == b'_')
+}
+
+async fn authenticate(conn: &mut Conn<states::Login>, online_mode: &OnlineMode, username: &str) -> io::Result<Profile> {
+    let key = &online_mode.key;
+    let verify_token: [u8; 4] = rand::random();
+    conn.send_packet(clientbound::login::EncryptionRequest {
+        server_id: String::new(),
+        public_key: key.public_key_der().to_vec(),
+        verify_token: verify_token.to_vec(),
+    }).await?;
+
+    let response = match conn.read_packet().await? {
+        serverbound::login::Packet::EncryptionResponse(response) => response,
+        _ => return Err(invalid_data("expected encryption response")),
+    };
+    if key.decrypt(&response.verify_token)? != verify_token {
+        return Err(invalid_data("verify token doesn't match"));
+    }
+    let shared_secret: [u8; 16] = key.decrypt(&response.shared_secret)?
+        .try_into()
+        .map_err(|_| invalid_data("shared secret is the wrong length"))?;
+    conn.enable_encryption(&shared_secret).await?;
+
+    let server_hash = server_id_hash("", &shared_secret, key.public_key_der());
+    // what vanilla says
+    online_mode.authenticator.has_joined(username, &server_hash).await?
+        .ok_or_else(|| Kick(TextComponent::text("Failed to verify username!")).into())
+}
+
+async fn login_profile(conn: &mut Conn<states::Login>, config: &LoginConfig) -> io::Result<Profile> {
+    // set compression sends it as an i32
+    let threshold = config.compression_threshold.map(i32::try_from).transpose()
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "compression threshold is too big"))?;
+    let username = match conn.read_packet().await? {
+        serverbound::login::Packet::LoginStart(start) => start.name,
+        _ => return Err(invalid_data("expected login start")),
+    };
+    if conn.version().is_none() {
+        return Err(Kick(TextComponent::text(versions::unsupported_message())).into());
+    }
+    if !valid_username(&username) {
+        return Err(Kick(TextComponent::text("Invalid username")).into());
+    }
+    let profile = match &config.online_mode {
+        Some(online_mode) => authenticate(conn, online_mode, &username).await?,
+        None => Profile {
+            uuid: offline_uuid(&username),
+            username,
+            properties: Vec::new(),
+        },
+    };
+
+    if let Some(threshold) = threshold {
+        conn.send_packet(clientbound::login::SetCompression { threshold }).await?;
+        conn.enable_compression(threshold as usize);
+    }
+
+    conn.send_packet(clientbound::login::LoginSuccess {
+        uuid: profile.uuid,
+        username: profile.username.clone(),
+    }).await?;
+    Ok(profile)
+}
+
+// takes a connection from right after the handshake to the start of play
+// the client gets kicked with a reason if it fails
+pub async fn login(mut conn: Conn<states::Login>, config: &LoginConfig) -> io::Result<(Conn<Play>, Profile)> {
+    match login_profile(&mut conn, config).await {
+        Ok(profile) => Ok((conn.into_play(), profile)),
+        Err(err) => {
+            // the original error says more than one from kicking would
+            let _ = conn.disconnect_with_error(&err).await;
+            Err(err)
+        },
+    }
+}
@@ -0,0 +1,363 @@
+use std::marker::PhantomData;
+use std::time::Duration;
+use tokio::io;
+use tokio::net::TcpStream;
+use crate::chat::TextComponent;
+use crate::connection::{self, Send, Recv, PacketOrLegacyPing, Limits};
+use crate::legacy::{self, LegacyPing};
+use crate::de::{Deserialize, ByteReader};
+use crate::ser::{Serialize, ByteWriter};
+use crate::packets::{serverbound, clientbound};
+use crate::packets::serverbound::handshake::{Handshake, HandshakeNextState};
+use crate::versions::{self, Version, StateKind};
+
+pub mod login;
+pub mod play;
+pub mod status;
+
+// a connection state, decides which packets can be read and written
+pub trait State {
+    type Serverbound: for<'de> Deserialize<'de, Value = Self::Serverbound>;
+    type Clientbound: Serialize<Value = Self::Clientbound>;
+    const KIND: StateKind;
+}
+
+// the server never sends anything during the handshake
+pub enum NoPackets {}
+
+impl Serialize for NoPackets {
+    type Value = Self;
+
+    fn serialize(value: &Self, _output: &mut ByteWriter<'_>) {
+        match *value {}
+    }
+}
+
+pub struct Handshaking;
+pub struct Status;
+pub struct Login;
+pub struct Play;
+
+impl State for Handshaking {
+    type Serverbound = serverbound::handshake::Packet;
+    type Clientbound = NoPackets;
+    const KIND: StateKind = StateKind::Handshaking;
+}
+
+impl State for Status {
+    type Serverbound = serverbound::status::Packet;
+    type Clientbound = clientbound::status::Packet;
+    const KIND: StateKind = StateKind::Status;
+}
+
+impl State for Login {
+    type Serverbound = serverbound::login::Packet;
+    type Clientbound = clientbound::login::Packet;
+    const KIND: StateKind = StateKind::Login;
+}
+
+impl State for Play {
+    type Serverbound = serverbound::play::Packet;
+    type Clientbound = clientbound::play::Packet;
+    const KIND: StateKind = StateKind::Play;
+}
+
+// states with a packet for kicking the client with a reason
+pub trait Kickable: State {
+    fn disconnect_packet(reason: TextComponent) -> Self::Clientbound;
+}
+
+impl Kickable for Login {
+    fn disconnect_packet(reason: TextComponent) -> Self::Clientbound {
+        clientbound::login::Disconnect { reason }.into()
+    }
+}
+
+impl Kickable for Play {
+    fn disconnect_packet(reason: TextComponent) -> Self::Clientbound {
+        clientbound::play::Disconnect { reason }.into()
+    }
+}
+
+// an error that should kick the client with this reason, it's the inner error of an io::Error
+#[derive(Clone, Debug)]
+pub struct Kick(pub TextComponent);
+
+impl std::fmt::Display for Kick {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "kicked: {}", self.0)
+    }
+}
+
+impl std::error::Error for Kick {}
+
+impl From<Kick> for io::Error {
+    fn from(kick: Kick) -> Self {
+        io::Error::other(kick)
+    }
+}
+
+// what to tell the client when handling its connection fails
+// None if it's gone already, so there's no one to tell
+pub fn kick_reason(err: &io::Error) -> Option<TextComponent> {
+    match err.kind() {
+        io::ErrorKind::UnexpectedEof
+        | io::ErrorKind::ConnectionReset
+        | io::ErrorKind::ConnectionAborted
+        | io::ErrorKind::BrokenPipe => return None,
+        _ => {},
+    }
+    if let Some(Kick(reason)) = err.get_ref().and_then(|inner| inner.downcast_ref()) {
+        return Some(reason.clone());
+    }
+    Some(TextComponent::text(match err.kind() {
+        // bad packets and frames, the message says what was wrong with them
+        io::ErrorKind::InvalidData => format!("Invalid packet: {}", err),
+        io::ErrorKind::TimedOut => "Timed out".to_owned(),
+        _ => "Internal server error".to_owned(),
+    }))
+}
+
+async fn read_packet<S: State>(recv: &mut Recv, version: &Version) -> io::Result<S::Serverbound> {
+    let data = recv.read_packet_data().await?;
+    let data = version.translate_serverbound(S::KIND, data)?;
+    S::Serverbound::deserialize(&mut ByteReader::new(&data)).map_err(io::Error::from)
+}
+
+async fn write_packet<S: State>(send: &mut Send, version: &Version, packet: &S::Clientbound) -> io::Result<()> {
+    let mut data = Vec::new();
+    S::Clientbound::serialize(packet, &mut ByteWriter::new(&mut data));
+    let data = version.translate_clientbound(S::KIND, &data)?;
+    send.write_packet_bytes(&data).await
+}
+
+// how long to wait for the client to close its side once we're done
+const CLOSE_WAIT: Duration = Duration::from_secs(1);
+
+pub struct Conn<S: State> {
+    send: Send,
+    recv: Recv,
+    // what the client said in its handshake
+    protocol: i32,
+    // None if that isn't supported, packets use the canonical format then
+    version: Option<&'static Version>,
+    state: PhantomData<S>,
+}
+
+impl<S: State> Conn<S> {
+    fn into_state<T: State>(self) -> Conn<T> {
+        Conn {
+            send: self.send,
+            recv: self.recv,
+            protocol: self.protocol,
+            version: self.version,
+            state: PhantomData,
+        }
+    }
+
+    pub fn protocol(&self) -> i32 {
+        self.protocol
+    }
+
+    pub fn version(&self) -> Option<&'static Version> {
+        self.version
+    }
+
+    // how big the client's packets can be, see connection::Limits
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.recv.set_limits(limits);
+    }
+
+    fn wire_version(&self) -> &'static Version {
+        self.version.unwrap_or_else(versions::canonical)
+    }
+
+    pub async fn read_packet(&mut self) -> io::Result<S::Serverbound> {
+        let version = self.wire_version();
+        read_packet::<S>(&mut self.recv, version).await
+    }
+
+    // buffers the packet without flushing, see Send::write_packet
+    pub async fn write_packet(&mut self, packet: impl Into<S::Clientbound>) -> io::Result<()> {
+        let version = self.wire_version();
+        write_packet::<S>(&mut self.send, version, &packet.into()).await
+    }
+
+    pub async fn flush(&mut self) -> io::Result<()> {
+        self.send.flush().await
+    }
+
+    pub async fn send_packet(&mut self, packet: impl Into<S::Clientbound>) -> io::Result<()> {
+        self.write_packet(packet).await?;
+        self.flush().await
+    }
+
+    pub async fn shutdown(self) -> io::Result<()> {
+        self.send.shutdown().await
+    }
+
+    // reading can't be cancelled partway through a packet, so anything that reads
+    // while waiting on something else needs the reading in its own task
+    pub fn into_split(self) -> (ConnReader<S>, ConnWriter<S>) {
+        let version = self.wire_version();
+        let reader = ConnReader { recv: self.recv, version, state: PhantomData };
+        let writer = ConnWriter { send: self.send, version, state: PhantomData };
+        (reader, writer)
+    }
+
+    // shuts down like shutdown, then waits for the client to close its side
+    // dropping the socket while the client is still sending makes it send a reset,
+    // and the client can lose the last packets to that before it reads them
+    async fn close(self) -> io::Result<()> {
+        let mut recv = self.recv;
+        self.send.shutdown().await?;
+        // errors here just mean the client is gone already
+        let _ = tokio::time::timeout(CLOSE_WAIT, recv.drain()).await;
+        Ok(())
+    }
+}
+
+impl<S: Kickable> Conn<S> {
+    // kicks the client, who gets shown the reason
+    pub async fn disconnect(mut self, reason: impl Into<TextComponent>) -> io::Result<()> {
+        self.write_packet(S::disconnect_packet(reason.into())).await?;
+        self.close().await
+    }
+
+    // kicks the client with kick_reason for err, or just closes if it's gone already
+    pub async fn disconnect_with_error(self, err: &io::Error) -> io::Result<()> {
+        match kick_reason(err) {
+            Some(reason) => self.disconnect(reason).await,
+            None => self.close().await,
+        }
+    }
+}
+
+// the halves from Conn::into_split
+pub struct ConnReader<S: State> {
+    recv: Recv,
+    version: &'static Version,
+    state: PhantomData<S>,
+}
+
+pub struct ConnWriter<S: State> {
+    send: Send,
+    version: &'static Version,
+    state: PhantomData<S>,
+}
+
+impl<S: State> ConnReader<S> {
+    pub async fn read_packet(&mut self) -> io::Result<S::Serverbound> {
+        read_packet::<S>(&mut self.recv, self.version).await
+    }
+}
+
+impl<S: State> ConnWriter<S> {
+    pub async fn write_packet(&mut self, packet: impl Into<S::Clientbound>) -> io::Result<()> {
+        write_packet::<S>(&mut self.send, self.version, &packet.into()).await
+    }
+
+    pub async fn flush(&mut self) -> io::Result<()> {
+        self.send.flush().await
+    }
+
+    pub async fn send_packet(&mut self, packet: impl Into<S::Clientbound>) -> io::Result<()> {
+        self.write_packet(packet).await?;
+        self.flush().await
+    }
+
+    pub async fn shutdown(self) -> io::Result<()> {
+        self.send.shutdown().await
+    }
+}
+
+// unlike Conn these can't wait for the client to close, keep reading
+// from the ConnReader until it does to close cleanly
+impl<S: Kickable> ConnWriter<S> {
+    pub async fn disconnect(mut self, reason: impl Into<TextComponent>) -> io::Result<()> {
+        self.write_packet(S::disconnect_packet(reason.into())).await?;
+        self.shutdown().await
+    }
+
+    pub async fn disconnect_with_error(self, err: &io::Error) -> io::Result<()> {
+        match kick_reason(err) {
+            Some(reason) => self.disconnect(reason).await,
+            None => self.shutdown().await,
+        }
+    }
+}
+
+pub enum HandshakeOrLegacyPing {
+    Handshake(serverbound::handshake::Handshake),
+    LegacyPing(LegacyPing),
+}
+
+pub enum NextState {
+    Status(Conn<Status>),
+    Login(Conn<Login>),
+}
+
+impl Conn<Handshaking> {
+    pub fn new(conn: TcpStream) -> Self {
+        let (send, recv) = connection::make_conn(conn);
+        Self {
+            send,
+            recv,
+            protocol: versions::canonical().protocol,
+            version: Some(versions::canonical()),
+            state: PhantomData,
+        }
+    }
+
+    // the first packet can be a legacy ping, which doesn't have the usual framing
+    pub async fn read_handshake(&mut self) -> io::Result<HandshakeOrLegacyPing> {
+        match self.recv.read_packet_or_legacy_ping().await? {
+            PacketOrLegacyPing::Packet(packet) => {
+                let data = packet.read_all().await?;
+                let packet = serverbound::handshake::Packet::deserialize(&mut ByteReader::new(&data))?;
+                match packet {
+                    serverbound::handshake::Packet::Handshake(handshake) => Ok(HandshakeOrLegacyPing::Handshake(handshake)),
+                }
+            },
+            PacketOrLegacyPing::LegacyPing(ping) => Ok(HandshakeOrLegacyPing::LegacyPing(ping)),
+        }
+    }
+
+    // legacy packets don't get framed, and the connection is over once one is sent
+    pub async fn send_legacy_packet(mut self, data: &[u8]) -> io::Result<()> {
+        self.send.send_unframed(data).await?;
+        self.shutdown().await
+    }
+
+    // the kick packet from before 1.7, for answering a legacy ping with an error
+    pub async fn disconnect_legacy(mut self, reason: impl Into<TextComponent>) -> io::Result<()> {
+        self.send.send_unframed(&legacy::kick_packet(&reason.into().to_legacy())).await?;
+        self.close().await
+    }
+
+    // the handshake picks the protocol version for the rest of the connection,
+    // Conn::version is None from then on if it isn't supported
+    pub fn next_state(mut self, handshake: &Handshake) -> NextState {
+        self.protocol = handshake.version;
+        self.version = versions::find(handshake.version);
+        match handshake.next_state {
+            HandshakeNextState::Status => NextState::Status(self.into_state()),
+            HandshakeNextState::Login => NextState::Login(self.into_state()),
+        }
+    }
+}
+
+impl Conn<Login> {
+    pub fn enable_compression(&mut self, threshold: usize) {
+        connection::enable_compression(&mut self.send, &mut self.recv, threshold);
+    }
+
+    pub async fn enable_encryption(&mut self, shared_secret: &[u8; 16]) -> io::Result<()> {
+        connection::enable_encryption(&mut self.send, &mut self.recv, shared_secret).await
+    }
+
+    // only call once Login Success has been sent
+    pub fn into_play(self) -> Conn<Play> {
+        self.into_state()
+    }
+}
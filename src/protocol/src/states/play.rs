@@ -0,0 +1,361 @@
+use std::time::Duration;
+use serde::Serialize;
+use tokio::io;
+use tokio::sync::mpsc;
+use crate::chat::TextComponent;
+use crate::de;
+use crate::nbt::{self, Nbt};
+use crate::packets::{serverbound, clientbound, PROTOCOL_VERSION};
+use crate::packets::clientbound::play::{ChunkData, Gamemode};
+use crate::ser::{self, ByteWriter};
+use crate::states::{Conn, Play, Kick, CLOSE_WAIT};
+use crate::types::Position;
+use crate::varnum::VarInt;
+use crate::versions;
+
+// the registry entry that sets a world's height, lighting and sky
+// this gets serialized to nbt as-is, so field names match what the client expects
+#[derive(Clone, Debug, Serialize)]
+pub struct DimensionType {
+    pub piglin_safe: bool,
+    pub natural: bool,
+    pub ambient_light: f32,
+    // the time of day is stuck at this if it's set
+    pub fixed_time: Option<i64>,
+    pub infiniburn: String,
+    pub respawn_anchor_works: bool,
+    pub has_skylight: bool,
+    pub bed_works: bool,
+    pub effects: String,
+    pub has_raids: bool,
+    // these two have to be multiples of 16, clients before 1.17 always use 0 and 256
+    pub min_y: i32,
+    pub height: i32,
+    pub logical_height: i32,
+    pub coordinate_scale: f64,
+    pub ultrawarm: bool,
+    pub has_ceiling: bool,
+}
+
+impl Default for DimensionType {
+    // the overworld, but only as tall as before 1.18
+    fn default() -> Self {
+        Self {
+            piglin_safe: false,
+            natural: true,
+            ambient_light: 0.0,
+            fixed_time: None,
+            infiniburn: "minecraft:infiniburn_overworld".to_owned(),
+            respawn_anchor_works: false,
+            has_skylight: true,
+            bed_works: true,
+            effects: "minecraft:overworld".to_owned(),
+            has_raids: true,
+            min_y: 0,
+            height: 256,
+            logical_height: 256,
+            coordinate_scale: 1.0,
+            ultrawarm: false,
+            has_ceiling: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Biome {
+    pub precipitation: String,
+    pub temperature: f32,
+    pub downfall: f32,
+    pub category: String,
+    // only clients before 1.18 read these
+    pub depth: f32,
+    pub scale: f32,
+    pub effects: BiomeEffects,
+}
+
+// colors are 0xrrggbb
+#[derive(Clone, Debug, Serialize)]
+pub struct BiomeEffects {
+    pub sky_color: i32,
+    pub fog_color: i32,
+    pub water_color: i32,
+    pub water_fog_color: i32,
+}
+
+impl Default for Biome {
+    // plains
+    fn default() -> Self {
+        Self {
+            precipitation: "rain".to_owned(),
+            temperature: 0.8,
+            downfall: 0.4,
+            category: "plains".to_owned(),
+            depth: 0.125,
+            scale: 0.05,
+            effects: BiomeEffects {
+                sky_color: 0x78a7ff,
+                fog_color: 0xc0d8ff,
+                water_color: 0x3f76e4,
+                water_fog_color: 0x050533,
+            },
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Registry<'a, T> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    value: Vec<RegistryEntry<'a, T>>,
+}
+
+#[derive(Serialize)]
+struct RegistryEntry<'a, T> {
+    name: &'a str,
+    id: i32,
+    element: &'a T,
+}
+
+impl<'a, T> Registry<'a, T> {
+    // ids go in the order the entries are given
+    fn new(kind: &'static str, entries: &[(&'a str, &'a T)]) -> Self {
+        let value = entries.iter().zip(0..).map(|(&(name, element), id)| RegistryEntry { name, id, element }).collect();
+        Self { kind, value }
+    }
+}
+
+#[derive(Serialize)]
+struct DimensionCodec<'a> {
+    #[serde(rename = "minecraft:dimension_type")]
+    dimension_types: Registry<'a, DimensionType>,
+    #[serde(rename = "minecraft:worldgen/biome")]
+    biomes: Registry<'a, Biome>,
+}
+
+// the registries that go in join game, by name
+// clients before 1.18 need there to be a minecraft:plains biome
+pub fn dimension_codec(dimension_types: &[(&str, &DimensionType)], biomes: &[(&str, &Biome)]) -> Nbt {
+    let codec = DimensionCodec {
+        dimension_types: Registry::new("minecraft:dimension_type", dimension_types),
+        biomes: Registry::new("minecraft:worldgen/biome", biomes),
+    };
+    // can't fail, everything in here has an nbt form
+    Nbt::new(nbt::to_compound(&codec).unwrap())
+}
+
+// a chunk with nothing in it, all air and all the first biome
+pub fn empty_chunk(x: i32, z: i32, dimension: &DimensionType) -> ChunkData {
+    let mut data = Vec::new();
+    let mut output = ByteWriter::new(&mut data);
+    for _ in 0..dimension.height / 16 {
+        // how many blocks aren't air
+        <i16 as ser::Serialize>::serialize(&0, &mut output);
+        // the block states then the biomes, with 0 bits per entry they're just
+        // the one value in the palette and an empty array
+        for _ in 0..2 {
+            output.write_byte(0);
+            <VarInt as ser::Serialize>::serialize(&0, &mut output);
+            <VarInt as ser::Serialize>::serialize(&0, &mut output);
+        }
+    }
+    ChunkData {
+        x,
+        z,
+        heightmaps: Nbt::default(),
+        data,
+        block_entities: Vec::new(),
+        trust_edges: true,
+        sky_light_mask: Vec::new(),
+        block_light_mask: Vec::new(),
+        empty_sky_light_mask: Vec::new(),
+        empty_block_light_mask: Vec::new(),
+        sky_light: Vec::new(),
+        block_light: Vec::new(),
+    }
+}
+
+// the world the client gets put in by join
+#[derive(Clone, Debug)]
+pub struct JoinConfig {
+    pub entity_id: i32,
+    pub gamemode: Gamemode,
+    // starts the player off flying, whatever the gamemode
+    pub flying: bool,
+    pub world_name: String,
+    pub dimension_name: String,
+    pub dimension: DimensionType,
+    pub view_distance: i32,
+    // where the player appears, it has to be inside the dimension's height
+    pub spawn: (f64, f64, f64),
+}
+
+impl Default for JoinConfig {
+    fn default() -> Self {
+        Self {
+            entity_id: 1,
+            gamemode: Gamemode::Creative,
+            flying: true,
+            world_name: "mckerel:void".to_owned(),
+            dimension_name: "minecraft:overworld".to_owned(),
+            dimension: DimensionType::default(),
+            view_distance: 4,
+            spawn: (0.5, 64.0, 0.5),
+        }
+    }
+}
+
+// the bits in player abilities
+const INVULNERABLE: i8 = 0x01;
+const FLYING: i8 = 0x02;
+const ALLOW_FLYING: i8 = 0x04;
+const INSTANT_BREAK: i8 = 0x08;
+
+impl JoinConfig {
+    fn abilities(&self) -> i8 {
+        let flags = match self.gamemode {
+            Gamemode::Creative => INVULNERABLE | ALLOW_FLYING | INSTANT_BREAK,
+            Gamemode::Spectator => INVULNERABLE | ALLOW_FLYING | FLYING,
+            _ => 0,
+        };
+        if self.flying {
+            flags | ALLOW_FLYING | FLYING
+        } else {
+            flags
+        }
+    }
+}
+
+// takes a connection from right after login to the player being in the world,
+// the chunks around the spawn are sent empty
+// chunks can't be translated to older versions, so their clients get kicked instead
+pub async fn join(conn: &mut Conn<Play>, config: &JoinConfig) -> io::Result<()> {
+    if conn.version().map(|version| version.protocol) != Some(PROTOCOL_VERSION) {
+        let message = format!("This server needs Minecraft {}", versions::canonical().name);
+        return Err(Kick(TextComponent::text(message)).into());
+    }
+    let dimension = Nbt::new(nbt::to_compound(&config.dimension).unwrap());
+    conn.write_packet(clientbound::play::JoinGame {
+        entity_id: config.entity_id,
+        hardcore: false,
+        gamemode: config.gamemode,
+        previous_gamemode: -1,
+        world_names: vec![config.world_name.clone()],
+        dimension_codec: dimension_codec(&[(&config.dimension_name, &config.dimension)], &[("minecraft:plains", &Biome::default())]),
+        dimension,
+        world_name: config.world_name.clone(),
+        hashed_seed: 0,
+        max_players: 0,
+        view_distance: config.view_distance,
+        simulation_distance: config.view_distance,
+        reduced_debug_info: false,
+        enable_respawn_screen: true,
+        debug: false,
+        flat: true,
+    }).await?;
+    // vanilla's speeds
+    conn.write_packet(clientbound::play::PlayerAbilities {
+        flags: config.abilities(),
+        flying_speed: 0.05,
+        fov_modifier: 0.1,
+    }).await?;
+    conn.write_packet(clientbound::play::HeldItemChange { slot: 0 }).await?;
+
+    let (x, y, z) = config.spawn;
+    let block = Position::new(x.floor() as i32, y.floor() as i32, z.floor() as i32);
+    let (chunk_x, chunk_z) = (block.x >> 4, block.z >> 4);
+    conn.write_packet(clientbound::play::UpdateViewPosition { chunk_x, chunk_z }).await?;
+    let distance = config.view_distance;
+    for x in chunk_x - distance..=chunk_x + distance {
+        for z in chunk_z - distance..=chunk_z + distance {
+            conn.write_packet(empty_chunk(x, z, &config.dimension)).await?;
+        }
+    }
+
+    conn.write_packet(clientbound::play::SpawnPosition { location: block, angle: 0.0 }).await?;
+    // this is what takes the client off the loading screen
+    conn.write_packet(clientbound::play::PlayerPositionAndLook {
+        x,
+        y,
+        z,
+        yaw: 0.0,
+        pitch: 0.0,
+        relative: 0,
+        teleport_id: 0,
+        dismount_vehicle: false,
+    }).await?;
+    conn.flush().await
+}
+
+// vanilla sends one this often, and kicks clients that haven't answered by the next
+pub const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+// only keep alives have types so far, everything else the client sends gets skipped
+fn unknown_packet(err: &io::Error) -> bool {
+    let err = err.get_ref().and_then(|inner| inner.downcast_ref::<de::Error>());
+    matches!(err.map(de::Error::kind), Some(de::ErrorKind::BadPacketId(_)))
+}
+
+// joins, then keeps the player in an empty world until they leave
+// nothing happens there, so all that's left to do is keep alives
+pub async fn void_world(mut conn: Conn<Play>, config: &JoinConfig) -> io::Result<()> {
+    if let Err(err) = join(&mut conn, config).await {
+        let _ = conn.disconnect_with_error(&err).await;
+        return Err(err);
+    }
+
+    let (mut reader, mut writer) = conn.into_split();
+    // reading isn't cancel safe, so it can't go in the select
+    let (packets_tx, mut packets) = mpsc::channel(16);
+    let reading = tokio::spawn(async move {
+        loop {
+            let packet = match reader.read_packet().await {
+                Err(err) if unknown_packet(&err) => continue,
+                packet => packet,
+            };
+            let done = packet.is_err();
+            if packets_tx.send(packet).await.is_err() || done {
+                break;
+            }
+        }
+    });
+
+    let mut keep_alive = tokio::time::interval(KEEP_ALIVE_INTERVAL);
+    let mut pending = None;
+    let res: io::Result<()> = loop {
+        tokio::select! {
+            packet = packets.recv() => match packet {
+                Some(Ok(serverbound::play::Packet::KeepAlive(serverbound::play::KeepAlive(id)))) => {
+                    if pending == Some(id) {
+                        pending = None;
+                    }
+                },
+                Some(Ok(_)) => {},
+                Some(Err(err)) => break Err(err),
+                None => unreachable!("the reader only stops after sending an error"),
+            },
+            _ = keep_alive.tick() => {
+                if pending.is_some() {
+                    break Err(io::ErrorKind::TimedOut.into());
+                }
+                let id = rand::random();
+                if let Err(err) = writer.send_packet(clientbound::play::KeepAlive(id)).await {
+                    break Err(err);
+                }
+                pending = Some(id);
+            },
+        }
+    };
+    // clients leave by closing the connection
+    let res = match res {
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => Ok(()),
+        res => res,
+    };
+
+    if let Err(err) = &res {
+        let _ = writer.disconnect_with_error(err).await;
+    }
+    // wait for the client to close its side, like Conn::disconnect does
+    let _ = tokio::time::timeout(CLOSE_WAIT, async { while packets.recv().await.is_some() {} }).await;
+    reading.abort();
+    res
+}
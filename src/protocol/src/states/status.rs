@@ -0,0 +1,157 @@
+use tokio::io;
+use serde::Serialize;
+use crate::chat::TextComponent;
+use crate::connection::invalid_data;
+use crate::legacy::{self, LegacyPing, LegacyServerInfo};
+use crate::packets::{serverbound, clientbound, PROTOCOL_VERSION};
+use crate::states::{self, Conn};
+use crate::types::Uuid;
+use crate::versions;
+
+// what shows up in the multiplayer list
+// this gets serialized to json as-is, so field names match what the client expects
+#[derive(Serialize)]
+pub struct ServerStatus {
+    pub version: Version,
+    pub players: Players,
+    pub description: TextComponent,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub favicon: Option<Favicon>,
+}
+
+#[derive(Serialize)]
+pub struct Version {
+    pub name: String,
+    pub protocol: i32,
+}
+
+impl Default for Version {
+    // the name is only shown to clients that can't join, so it names every supported version
+    fn default() -> Self {
+        let supported = versions::supported();
+        let name = match supported {
+            [only] => only.name.to_owned(),
+            _ => format!("{}-{}", supported[supported.len() - 1].name, supported[0].name),
+        };
+        Self {
+            name,
+            protocol: PROTOCOL_VERSION,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct Players {
+    pub max: i32,
+    pub online: i32,
+    // shown when hovering over the player count
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub sample: Vec<PlayerSample>,
+}
+
+#[derive(Serialize)]
+pub struct PlayerSample {
+    pub name: String,
+    // hyphenated
+    pub id: String,
+}
+
+impl PlayerSample {
+    pub fn new(name: impl Into<String>, uuid: Uuid) -> Self {
+        Self {
+            name: name.into(),
+            id: uuid.to_string(),
+        }
+    }
+}
+
+// a 64x64 png, as a data url
+#[derive(Serialize)]
+#[serde(transparent)]
+pub struct Favicon(String);
+
+impl Favicon {
+    pub fn from_png(png: &[u8]) -> io::Result<Self> {
+        use base64::Engine;
+
+        const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+        // the IHDR chunk is always first, and starts with the width and height
+        if png.len() < 24 || png[..8] != PNG_SIGNATURE || &png[12..16] != b"IHDR" {
+            return Err(invalid_data("favicon isn't a png"));
+        }
+        if png[16..20] != 64u32.to_be_bytes() || png[20..24] != 64u32.to_be_bytes() {
+            return Err(invalid_data("favicon isn't 64x64"));
+        }
+        let encoded = base64::engine::general_purpose::STANDARD.encode(png);
+        Ok(Self(format!("data:image/png;base64,{}", encoded)))
+    }
+}
+
+impl ServerStatus {
+    pub fn new(motd: &str, max_players: i32, online_players: i32) -> Self {
+        Self {
+            version: Version::default(),
+            players: Players {
+                max: max_players,
+                online: online_players,
+                sample: Vec::new(),
+            },
+            description: TextComponent::text(motd),
+            favicon: None,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        // can't fail, everything in here serializes fine
+        serde_json::to_string(self).unwrap()
+    }
+
+    // the same, but reporting a different protocol number
+    pub fn to_json_with_protocol(&self, protocol: i32) -> String {
+        let mut json = serde_json::to_value(self).unwrap();
+        json["version"]["protocol"] = protocol.into();
+        json.to_string()
+    }
+
+    pub fn legacy_info(&self) -> LegacyServerInfo {
+        LegacyServerInfo {
+            protocol: self.version.protocol,
+            version: self.version.name.clone(),
+            // legacy clients only understand § codes
+            motd: self.description.to_legacy(),
+            online: self.players.online,
+            max: self.players.max,
+        }
+    }
+}
+
+// answers status requests and pings until the client is done
+pub async fn respond(mut conn: Conn<states::Status>, status: &ServerStatus) -> io::Result<()> {
+    loop {
+        let packet = match conn.read_packet().await {
+            Ok(packet) => packet,
+            // clients are allowed to hang up without pinging
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(err) => return Err(err),
+        };
+        match packet {
+            serverbound::status::Packet::Request(_) => {
+                // supported clients get told their own protocol, otherwise they'd show the server as incompatible
+                let resp = match conn.version() {
+                    Some(version) => status.to_json_with_protocol(version.protocol),
+                    None => status.to_json(),
+                };
+                conn.send_packet(clientbound::status::Response { resp }).await?;
+            },
+            serverbound::status::Packet::Ping(ping) => {
+                conn.send_packet(clientbound::status::Pong(ping.0)).await?;
+                break;
+            }
+        }
+    }
+    conn.shutdown().await
+}
+
+pub async fn respond_legacy(conn: Conn<states::Handshaking>, ping: &LegacyPing, status: &ServerStatus) -> io::Result<()> {
+    conn.send_legacy_packet(&legacy::server_info_packet(ping, &status.legacy_info())).await
+}
@@ -0,0 +1,105 @@
+use crate::de::{self, Deserialize, ByteReader};
+use crate::ser::{Serialize, ByteWriter};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Uuid(pub u128);
+
+// hyphenated, like 069a79f4-44e9-4726-a5be-fca90e38aaf5
+impl std::fmt::Display for Uuid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let hex = format!("{:032x}", self.0);
+        write!(f, "{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+    }
+}
+
+impl Deserialize<'_> for Uuid {
+    type Value = Self;
+
+    fn deserialize(input: &mut ByteReader<'_>) -> de::Result<Self::Value> {
+        Ok(Self(u128::deserialize(input)?))
+    }
+}
+
+impl Serialize for Uuid {
+    type Value = Self;
+
+    fn serialize(value: &Self::Value, output: &mut ByteWriter<'_>) {
+        u128::serialize(&value.0, output);
+    }
+}
+
+// a block position, packed into a u64 as 26 bits of x, 26 of z, then 12 of y
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Position {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl Position {
+    pub fn new(x: i32, y: i32, z: i32) -> Self {
+        Self { x, y, z }
+    }
+
+    pub fn pack(&self) -> u64 {
+        ((self.x as u64 & 0x3ffffff) << 38) | ((self.z as u64 & 0x3ffffff) << 12) | (self.y as u64 & 0xfff)
+    }
+
+    pub fn unpack(packed: u64) -> Self {
+        // shifting as an i64 sign extends each field
+        let packed = packed as i64;
+        Self {
+            x: (packed >> 38) as i32,
+            y: (packed << 52 >> 52) as i32,
+            z: (packed << 26 >> 38) as i32,
+        }
+    }
+}
+
+impl Deserialize<'_> for Position {
+    type Value = Self;
+
+    fn deserialize(input: &mut ByteReader<'_>) -> de::Result<Self::Value> {
+        Ok(Self::unpack(u64::deserialize(input)?))
+    }
+}
+
+impl Serialize for Position {
+    type Value = Self;
+
+    fn serialize(value: &Self::Value, output: &mut ByteWriter<'_>) {
+        u64::serialize(&value.pack(), output);
+    }
+}
+
+// a rotation, in 1/256ths of a full turn
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Angle(pub u8);
+
+impl Angle {
+    pub fn from_degrees(degrees: f32) -> Self {
+        Self((degrees.rem_euclid(360.0) / 360.0 * 256.0).round() as i32 as u8)
+    }
+
+    pub fn to_degrees(self) -> f32 {
+        self.0 as f32 / 256.0 * 360.0
+    }
+}
+
+impl Deserialize<'_> for Angle {
+    type Value = Self;
+
+    fn deserialize(input: &mut ByteReader<'_>) -> de::Result<Self::Value> {
+        Ok(Self(u8::deserialize(input)?))
+    }
+}
+
+impl Serialize for Angle {
+    type Value = Self;
+
+    fn serialize(value: &Self::Value, output: &mut ByteWriter<'_>) {
+        u8::serialize(&value.0, output);
+    }
+}
+
+de::impl_to_owned_value_copy!(Uuid, Position, Angle);
@@ -1,4 +1,3 @@
-use num;
 use tokio::io::{AsyncRead, AsyncReadExt};
 
 pub struct ReaderState<T: num::PrimInt> {
@@ -87,6 +86,21 @@ impl<T: num::PrimInt> VarNumReader<T> {
     pub async fn read_from_async<R: AsyncRead + std::marker::Unpin>(self, read: R) -> std::io::Result<T> {
         Ok(self.read_from_async_get_state(read).await?.val)
     }
+
+    // negative numbers are written as their two's complement bit pattern,
+    // so the shifts have to be unsigned or this would never terminate
+    pub fn write_to(mut val: T, output: &mut Vec<u8>) {
+        let mask = T::from(0x7f).unwrap();
+        loop {
+            let byte = (val & mask).to_u8().unwrap();
+            val = val.unsigned_shr(7);
+            if val.is_zero() {
+                output.push(byte);
+                return;
+            }
+            output.push(byte | 0x80);
+        }
+    }
 }
 
 pub type VarInt = VarNumReader<i32>;
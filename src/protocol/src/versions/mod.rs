@@ -0,0 +1,112 @@
+use std::borrow::Cow;
+use crate::varnum::VarInt;
+use crate::de::{self, Deserialize, ByteReader};
+use crate::ser::{Serialize, ByteWriter};
+
+#[cfg(feature = "v1_17_1")]
+mod v1_17_1;
+#[cfg(feature = "v1_16_5")]
+mod v1_16_5;
+
+// which state a packet is from, since ids are only unique within one
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StateKind {
+    Handshaking,
+    Status,
+    Login,
+    Play,
+}
+
+// takes a whole packet (id and body) and returns None if it doesn't change
+pub(crate) type Translate = fn(StateKind, &[u8]) -> de::Result<Option<Vec<u8>>>;
+
+// one protocol version a client can connect with
+// everything else in the crate uses the canonical version (the one reexported from packets),
+// so packets get translated between that and this version's wire format
+// the translations get generated by build.rs from protocol/<version>.toml
+pub struct Version {
+    pub protocol: i32,
+    pub name: &'static str,
+    // from this version to the canonical one
+    pub(crate) serverbound: Translate,
+    // from the canonical version to this one
+    pub(crate) clientbound: Translate,
+}
+
+impl Version {
+    pub fn translate_serverbound<'a>(&self, state: StateKind, packet: &'a [u8]) -> de::Result<Cow<'a, [u8]>> {
+        Ok((self.serverbound)(state, packet)?.map_or(Cow::Borrowed(packet), Cow::Owned))
+    }
+
+    pub fn translate_clientbound<'a>(&self, state: StateKind, packet: &'a [u8]) -> de::Result<Cow<'a, [u8]>> {
+        Ok((self.clientbound)(state, packet)?.map_or(Cow::Borrowed(packet), Cow::Owned))
+    }
+}
+
+impl std::fmt::Debug for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.name, self.protocol)
+    }
+}
+
+// every enabled version, newest first
+pub fn supported() -> &'static [&'static Version] {
+    crate::packets::VERSIONS
+}
+
+pub fn canonical() -> &'static Version {
+    &crate::packets::VERSION
+}
+
+pub fn find(protocol: i32) -> Option<&'static Version> {
+    supported().iter().copied().find(|version| version.protocol == protocol)
+}
+
+// what unsupported clients get told when they try to log in
+pub fn unsupported_message() -> String {
+    let versions = supported();
+    match versions {
+        [only] => format!("Unsupported version, please use {}", only.name),
+        _ => format!("Unsupported version, please use {} to {}", versions[versions.len() - 1].name, versions[0].name),
+    }
+}
+
+// used by the generated translations
+
+pub(crate) fn unchanged(_state: StateKind, _packet: &[u8]) -> de::Result<Option<Vec<u8>>> {
+    Ok(None)
+}
+
+// the same body with a different id
+pub(crate) fn renumber(id: i32, input: &mut ByteReader<'_>) -> de::Result<Vec<u8>> {
+    let body = input.read_bytes(input.remaining_len())?;
+    let mut output = Vec::with_capacity(body.len() + 5);
+    VarInt::write_to(id, &mut output);
+    output.extend_from_slice(body);
+    Ok(output)
+}
+
+// a packet this version doesn't have
+pub(crate) fn missing() -> de::Result<Vec<u8>> {
+    Err(de::Error::new(de::ErrorKind::NotInVersion, 0))
+}
+
+// an id the canonical version uses for a packet this version numbers differently
+pub(crate) fn unknown(id: i32) -> de::Result<Vec<u8>> {
+    Err(de::Error::new(de::ErrorKind::BadPacketId(id), 0))
+}
+
+// a packet whose layout changed, F has to convert into T
+pub(crate) fn convert<F, T>(id: i32, input: &mut ByteReader<'_>) -> de::Result<Vec<u8>>
+where
+    F: for<'de> Deserialize<'de, Value = F> + Into<T>,
+    T: Serialize<Value = T>,
+{
+    let packet: T = F::deserialize(input)?.into();
+    input.check_done()?;
+    let mut output = Vec::new();
+    let mut writer = ByteWriter::new(&mut output);
+    VarInt::serialize(&id, &mut writer);
+    T::serialize(&packet, &mut writer);
+    Ok(output)
+}
@@ -0,0 +1,62 @@
+// conversions for the packets protocol/1.16.5.toml lists as changed
+use crate::packets::clientbound;
+use crate::packets::v1_16_5::clientbound as old;
+
+impl From<clientbound::play::MapData> for old::play::MapData {
+    fn from(packet: clientbound::play::MapData) -> Self {
+        Self {
+            map_id: packet.map_id,
+            scale: packet.scale,
+            tracking_position: packet.tracking_position,
+            locked: packet.locked,
+            icons: packet.icons.unwrap_or_default(),
+            columns: packet.columns,
+            rows: packet.rows,
+            x: packet.x,
+            z: packet.z,
+            data: packet.data,
+        }
+    }
+}
+
+impl From<clientbound::play::JoinGame> for old::play::JoinGame {
+    fn from(packet: clientbound::play::JoinGame) -> Self {
+        Self {
+            entity_id: packet.entity_id,
+            hardcore: packet.hardcore,
+            gamemode: packet.gamemode,
+            previous_gamemode: packet.previous_gamemode,
+            world_names: packet.world_names,
+            dimension_codec: packet.dimension_codec,
+            dimension: packet.dimension,
+            world_name: packet.world_name,
+            hashed_seed: packet.hashed_seed,
+            max_players: packet.max_players,
+            view_distance: packet.view_distance,
+            reduced_debug_info: packet.reduced_debug_info,
+            enable_respawn_screen: packet.enable_respawn_screen,
+            debug: packet.debug,
+            flat: packet.flat,
+        }
+    }
+}
+
+impl From<clientbound::play::PlayerPositionAndLook> for old::play::PlayerPositionAndLook {
+    fn from(packet: clientbound::play::PlayerPositionAndLook) -> Self {
+        Self {
+            x: packet.x,
+            y: packet.y,
+            z: packet.z,
+            yaw: packet.yaw,
+            pitch: packet.pitch,
+            relative: packet.relative,
+            teleport_id: packet.teleport_id,
+        }
+    }
+}
+
+impl From<clientbound::play::SpawnPosition> for old::play::SpawnPosition {
+    fn from(packet: clientbound::play::SpawnPosition) -> Self {
+        Self { location: packet.location }
+    }
+}
@@ -0,0 +1,25 @@
+// conversions for the packets protocol/1.17.1.toml lists as changed
+use crate::packets::clientbound;
+use crate::packets::v1_17_1::clientbound as old;
+
+impl From<clientbound::play::JoinGame> for old::play::JoinGame {
+    fn from(packet: clientbound::play::JoinGame) -> Self {
+        Self {
+            entity_id: packet.entity_id,
+            hardcore: packet.hardcore,
+            gamemode: packet.gamemode,
+            previous_gamemode: packet.previous_gamemode,
+            world_names: packet.world_names,
+            dimension_codec: packet.dimension_codec,
+            dimension: packet.dimension,
+            world_name: packet.world_name,
+            hashed_seed: packet.hashed_seed,
+            max_players: packet.max_players,
+            view_distance: packet.view_distance,
+            reduced_debug_info: packet.reduced_debug_info,
+            enable_respawn_screen: packet.enable_respawn_screen,
+            debug: packet.debug,
+            flat: packet.flat,
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use mckerel_protocol::chat::{TextComponent, Color, ClickEvent, HoverEvent, Style};
+use mckerel_protocol::clientbound::play::{ChatMessage, ChatPosition};
+use mckerel_protocol::de::{self, ErrorKind};
+use mckerel_protocol::ser::{Serialize, ByteWriter};
+use mckerel_protocol::types::Uuid;
+
+fn parse(json: &str) -> TextComponent {
+    serde_json::from_str(json).unwrap()
+}
+
+#[test]
+fn builder_json() {
+    let component = TextComponent::text("Hello ")
+        .color(Color::Gold)
+        .bold(true)
+        .child(TextComponent::keybind("key.jump").italic(false))
+        .child(TextComponent::text("!").color(Color::Rgb(0x12, 0xab, 0xff)).on_click(ClickEvent::OpenUrl("https://example.com".to_owned())));
+    assert_eq!(component.to_json(), concat!(
+        r#"{"text":"Hello ","color":"gold","bold":true,"extra":["#,
+        r#"{"keybind":"key.jump","italic":false},"#,
+        r##"{"text":"!","color":"#12abff","clickEvent":{"action":"open_url","value":"https://example.com"}}]}"##,
+    ));
+    assert_eq!(parse(&component.to_json()), component);
+
+    let hover = TextComponent::text("a").on_hover(HoverEvent::ShowText(Box::new("tip".into())));
+    assert_eq!(hover.to_json(), r#"{"text":"a","hoverEvent":{"action":"show_text","contents":{"text":"tip"}}}"#);
+    assert_eq!(parse(&hover.to_json()), hover);
+}
+
+#[test]
+fn other_json_forms() {
+    assert_eq!(parse(r#""plain""#), TextComponent::text("plain"));
+    assert_eq!(parse("12"), TextComponent::text("12"));
+    // the first element of an array is the parent of the rest
+    assert_eq!(
+        parse(r#"[{"text":"a","color":"red"},"b",{"translate":"chat.type.text","with":["jeb_","hi"]}]"#),
+        TextComponent::text("a").color(Color::Red)
+            .child("b")
+            .child(TextComponent::translate("chat.type.text", vec!["jeb_".into(), "hi".into()])),
+    );
+
+    let err = serde_json::from_str::<TextComponent>(r#"{"color":"red"}"#).err().unwrap();
+    assert!(err.to_string().starts_with("a chat component needs text, translate, score, selector or keybind"));
+    let err = serde_json::from_str::<TextComponent>(r#"{"text":"a","color":"pink"}"#).err().unwrap();
+    assert!(err.to_string().starts_with("invalid value: string \"pink\", expected a color"));
+}
+
+#[test]
+fn plain_text() {
+    let component = TextComponent::translate("%s <%2$s> %1$s 100%%", vec!["a".into(), TextComponent::text("b").child("c")])
+        .child(TextComponent::score("@p", "kills"));
+    assert_eq!(component.to_plain(), "a <bc> a 100%");
+    assert_eq!(component.to_string(), "a <bc> a 100%");
+}
+
+#[test]
+fn legacy() {
+    let component = TextComponent::from_legacy("§6§lGold §rplain §x§cred§");
+    assert_eq!(component, TextComponent::text("").child(TextComponent::text("Gold ").color(Color::Gold).bold(true))
+        .child("plain ")
+        .child(TextComponent::text("red§").color(Color::Red)));
+    assert_eq!(component.to_legacy(), "§6§lGold §rplain §cred§");
+    assert_eq!(TextComponent::from_legacy("no codes"), TextComponent::text("no codes"));
+
+    // children inherit their parent's style, and rgb becomes the closest named color
+    let component = TextComponent::text("a").bold(true)
+        .child(TextComponent::text("b").color(Color::Rgb(0xfe, 0x50, 0x50)))
+        .child(TextComponent::text("c").style(Style { bold: Some(false), ..Style::default() }));
+    assert_eq!(component.to_legacy(), "§la§c§lb§rc");
+}
+
+#[test]
+fn packet_field() {
+    let packet = ChatMessage {
+        message: TextComponent::text("hi").color(Color::Aqua),
+        position: ChatPosition::System,
+        sender: Uuid(0),
+    };
+    let mut bytes = Vec::new();
+    ChatMessage::serialize(&packet, &mut ByteWriter::new(&mut bytes));
+    let json = br#"{"text":"hi","color":"aqua"}"#;
+    assert_eq!(bytes[0] as usize, json.len());
+    assert_eq!(&bytes[1..=json.len()], json);
+    let read = de::deserialize_all::<ChatMessage>(&bytes).unwrap();
+    assert_eq!(read.message, packet.message);
+
+    // longer than other strings can be
+    let long = "a".repeat(40000);
+    let mut bytes = Vec::new();
+    TextComponent::serialize(&TextComponent::text(long.clone()), &mut ByteWriter::new(&mut bytes));
+    assert_eq!(de::deserialize_all::<TextComponent>(&bytes).unwrap().to_plain(), long);
+
+    let err = de::deserialize_all::<TextComponent>(b"\x03{}}").err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::BadJson);
+    assert_eq!(err.detail(), Some("a chat component needs text, translate, score, selector or keybind at line 1 column 2"));
+}
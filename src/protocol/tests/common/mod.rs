@@ -0,0 +1,49 @@
+// the connections the tests set up, in one place
+// not every test uses every one
+#![allow(dead_code)]
+use mckerel_protocol::{Send, Recv};
+use mckerel_protocol::serverbound::handshake::{Handshake, HandshakeNextState};
+use mckerel_protocol::states::{Conn, Status, Login, Play, NextState};
+use tokio::net::{TcpListener, TcpStream};
+
+// two ends of a socket, the server's first
+pub async fn connect() -> (TcpStream, TcpStream) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let client = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+    let (server, _) = listener.accept().await.unwrap();
+    (server, client)
+}
+
+pub fn handshake(version: i32, next_state: HandshakeNextState) -> Handshake {
+    Handshake {
+        version,
+        address: "localhost".to_owned(),
+        port: 25565,
+        next_state,
+    }
+}
+
+// the server's conn after the handshake, and the client's side of the socket
+pub async fn status_conn(version: i32) -> (Conn<Status>, (Send, Recv)) {
+    let (server, client) = connect().await;
+    let server = match Conn::new(server).next_state(&handshake(version, HandshakeNextState::Status)) {
+        NextState::Status(conn) => conn,
+        _ => unreachable!(),
+    };
+    (server, mckerel_protocol::make_conn(client))
+}
+
+pub async fn login_conn(version: i32) -> (Conn<Login>, (Send, Recv)) {
+    let (server, client) = connect().await;
+    let server = match Conn::new(server).next_state(&handshake(version, HandshakeNextState::Login)) {
+        NextState::Login(conn) => conn,
+        _ => unreachable!(),
+    };
+    (server, mckerel_protocol::make_conn(client))
+}
+
+// straight to play, without going through login
+pub async fn play_conn(version: i32) -> (Conn<Play>, (Send, Recv)) {
+    let (server, client) = login_conn(version).await;
+    (server.into_play(), client)
+}
@@ -0,0 +1,156 @@
+use std::io::Read;
+use mckerel_protocol::{Send, Recv, VarInt};
+use mckerel_protocol::de::{Deserialize, ByteReader};
+use mckerel_protocol::ser::{Serialize, ByteWriter};
+use mckerel_protocol::packets::serverbound;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+mod common;
+
+// the other side is a raw socket, so the exact bytes can be checked
+async fn connect() -> (TcpStream, (Send, Recv)) {
+    let (server, client) = common::connect().await;
+    (client, mckerel_protocol::make_conn(server))
+}
+
+async fn read_varint(stream: &mut TcpStream) -> i32 {
+    let mut bytes = Vec::new();
+    loop {
+        let byte = stream.read_u8().await.unwrap();
+        bytes.push(byte);
+        if byte & 0x80 == 0 {
+            return VarInt::deserialize(&mut ByteReader::new(&bytes)).unwrap();
+        }
+    }
+}
+
+async fn read_frame(stream: &mut TcpStream) -> Vec<u8> {
+    let len = read_varint(stream).await;
+    let mut frame = vec![0; len as usize];
+    stream.read_exact(&mut frame).await.unwrap();
+    frame
+}
+
+fn login_start(name: &str) -> serverbound::login::LoginStart {
+    serverbound::login::LoginStart { name: name.to_owned() }
+}
+
+#[tokio::test]
+async fn uncompressed_frames() {
+    let (mut client, (mut send, _recv)) = connect().await;
+    send.send_packet(&login_start("jeb_")).await.unwrap();
+    assert_eq!(read_frame(&mut client).await, [0x00, 4, b'j', b'e', b'b', b'_']);
+}
+
+#[tokio::test]
+async fn compressed_frames() {
+    let (mut client, (mut send, _recv)) = connect().await;
+    send.enable_compression(256);
+
+    // under the threshold, a data length of 0 and then the packet as it is
+    send.send_packet(&login_start("jeb_")).await.unwrap();
+    assert_eq!(read_frame(&mut client).await, [0, 0x00, 4, b'j', b'e', b'b', b'_']);
+
+    // over it, the uncompressed length and then zlib
+    let mut packet = vec![0x00];
+    packet.resize(301, 7);
+    send.write_packet_bytes(&packet).await.unwrap();
+    send.flush().await.unwrap();
+    let frame = read_frame(&mut client).await;
+    let mut reader = ByteReader::new(&frame);
+    assert_eq!(VarInt::deserialize(&mut reader).unwrap(), 301);
+    let compressed = reader.read_bytes(reader.remaining_len()).unwrap();
+    assert!(compressed.len() < 300);
+    let mut data = Vec::new();
+    flate2::read::ZlibDecoder::new(compressed).read_to_end(&mut data).unwrap();
+    assert_eq!(data, packet);
+}
+
+#[tokio::test]
+async fn batched_writes() {
+    let (mut client, (mut send, _recv)) = connect().await;
+    for name in ["a", "b", "c"] {
+        send.write_packet(&login_start(name)).await.unwrap();
+    }
+    // nothing goes out until the flush
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    let mut buf = [0; 16];
+    assert_eq!(client.try_read(&mut buf).err().unwrap().kind(), std::io::ErrorKind::WouldBlock);
+
+    send.flush().await.unwrap();
+    for name in [b'a', b'b', b'c'] {
+        assert_eq!(read_frame(&mut client).await, [0x00, 1, name]);
+    }
+}
+
+#[tokio::test]
+async fn compressed_below_the_threshold() {
+    let (mut client, (_send, mut recv)) = connect().await;
+    recv.enable_compression(256);
+    // a data length of 10 says the packet is compressed, but only packets of 256 or more can be
+    client.write_all(&[2, 10, 0]).await.unwrap();
+    let err = recv.read_packet().await.err().unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert_eq!(err.to_string(), "compressed packet is below the compression threshold");
+
+    let (mut client, (_send, mut recv)) = connect().await;
+    recv.enable_compression(256);
+    client.write_all(&[5, 0xff, 0xff, 0xff, 0xff, 0x0f]).await.unwrap();
+    let err = recv.read_packet().await.err().unwrap();
+    assert_eq!(err.to_string(), "negative data length");
+}
+
+// a compressed frame saying it has data_length bytes, around the zlib of data and then padding
+fn compressed_frame(data_length: i32, data: &[u8], padding: &[u8]) -> Vec<u8> {
+    let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+    std::io::Write::write_all(&mut encoder, data).unwrap();
+    let mut body = Vec::new();
+    VarInt::serialize(&data_length, &mut ByteWriter::new(&mut body));
+    body.extend(encoder.finish().unwrap());
+    body.extend_from_slice(padding);
+    let mut frame = Vec::new();
+    VarInt::serialize(&(body.len() as i32), &mut ByteWriter::new(&mut frame));
+    frame.extend(body);
+    frame
+}
+
+#[tokio::test]
+async fn compressed_frames_in_pieces() {
+    for piece in [1, 3, 7] {
+        let (mut client, (_send, mut recv)) = connect().await;
+        recv.enable_compression(16);
+        let packets: Vec<Vec<u8>> = (0..3).map(|i| vec![i; 300]).collect();
+        let data: Vec<u8> = packets.iter().flat_map(|packet| compressed_frame(300, packet, &[])).collect();
+        // the zlib checksum can come in its own read, it still has to be read as part of the frame
+        let client = tokio::spawn(async move {
+            client.set_nodelay(true).unwrap();
+            for chunk in data.chunks(piece) {
+                client.write_all(chunk).await.unwrap();
+                tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+            }
+            client
+        });
+        for packet in &packets {
+            assert_eq!(recv.read_packet_data().await.unwrap(), &packet[..], "{} byte pieces", piece);
+        }
+        drop(client.await.unwrap());
+    }
+}
+
+#[tokio::test]
+async fn compressed_data_length_mismatch() {
+    let cases: [(i32, usize, &[u8], &str); 3] = [
+        (200, 300, &[], "compressed packet is longer than its data length"),
+        (300, 200, &[], "compressed packet is shorter than its data length"),
+        (300, 300, &[0, 0], "compressed packet has data after its zlib stream"),
+    ];
+    for (data_length, len, padding, message) in cases {
+        let (mut client, (_send, mut recv)) = connect().await;
+        recv.enable_compression(16);
+        client.write_all(&compressed_frame(data_length, &vec![7; len], padding)).await.unwrap();
+        let err = recv.read_packet_data().await.err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), message);
+    }
+}
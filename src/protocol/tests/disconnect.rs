@@ -0,0 +1,79 @@
+use std::io;
+use mckerel_protocol::chat::TextComponent;
+use mckerel_protocol::de::{Deserialize, ByteReader};
+use mckerel_protocol::legacy;
+use mckerel_protocol::packets::{serverbound, clientbound};
+use mckerel_protocol::states::{self, Conn, Kick};
+use mckerel_protocol::states::login::{self, LoginConfig};
+use tokio::io::AsyncReadExt;
+
+mod common;
+
+#[tokio::test]
+async fn login_disconnect() {
+    let (server, (client_send, mut client_recv)) = common::login_conn(757).await;
+    let server = tokio::spawn(server.disconnect(TextComponent::text("Server closed")));
+    match client_recv.recv_packet().await.unwrap() {
+        clientbound::login::Packet::Disconnect(disconnect) => assert_eq!(disconnect.reason.to_plain(), "Server closed"),
+        _ => panic!("expected a disconnect"),
+    }
+    // nothing comes after it
+    let err = client_recv.read_packet().await.err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    drop((client_send, client_recv));
+    server.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn play_disconnect_is_translated() {
+    // 1.16.5, where play disconnect is 0x19 instead of 0x1a
+    let (server, (_client_send, mut client_recv)) = common::login_conn(754).await;
+    let server = tokio::spawn(server.into_play().disconnect("bye"));
+    let data = client_recv.read_packet().await.unwrap().read_all().await.unwrap();
+    assert_eq!(data[0], 0x19);
+    assert_eq!(&data[2..], br#"{"text":"bye"}"#);
+    // the server doesn't wait forever for the client to close
+    server.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn legacy_disconnect() {
+    let (server, mut client) = common::connect().await;
+    let server = Conn::new(server);
+    let server = tokio::spawn(server.disconnect_legacy(TextComponent::from_legacy("§cNo old clients")));
+    let mut data = Vec::new();
+    client.read_to_end(&mut data).await.unwrap();
+    assert_eq!(data, legacy::kick_packet("§cNo old clients"));
+    drop(client);
+    server.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn failed_login_kicks() {
+    let (server, (mut client_send, mut client_recv)) = common::login_conn(757).await;
+    let server = tokio::spawn(async move {
+        login::login(server, &LoginConfig::default()).await.map(|_| ())
+    });
+    client_send.send_packet(&serverbound::login::LoginStart { name: "not valid!".to_owned() }).await.unwrap();
+    match client_recv.recv_packet().await.unwrap() {
+        clientbound::login::Packet::Disconnect(disconnect) => assert_eq!(disconnect.reason.to_plain(), "Invalid username"),
+        _ => panic!("expected a disconnect"),
+    }
+    drop((client_send, client_recv));
+    let err = server.await.unwrap().err().unwrap();
+    assert_eq!(err.to_string(), "kicked: Invalid username");
+}
+
+#[test]
+fn kick_reasons() {
+    let reason = |err: io::Error| states::kick_reason(&err).map(|reason| reason.to_plain());
+
+    assert_eq!(reason(io::ErrorKind::UnexpectedEof.into()), None);
+    assert_eq!(reason(io::ErrorKind::ConnectionReset.into()), None);
+    assert_eq!(reason(io::ErrorKind::TimedOut.into()), Some("Timed out".to_owned()));
+    assert_eq!(reason(io::ErrorKind::PermissionDenied.into()), Some("Internal server error".to_owned()));
+    assert_eq!(reason(Kick("Server full".into()).into()), Some("Server full".to_owned()));
+
+    let err = serverbound::handshake::Packet::deserialize(&mut ByteReader::new(&[0x05])).err().unwrap();
+    assert_eq!(reason(err.into()), Some("Invalid packet: unknown packet id 0x05 (byte 0)".to_owned()));
+}
@@ -0,0 +1,99 @@
+use mckerel_protocol::encryption::{Encryptor, Decryptor, ServerKey, server_id_hash};
+use mckerel_protocol::clientbound::status::Response;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+mod common;
+
+// NIST SP 800-38A, F.3.7 and F.3.8 (CFB8-AES128)
+const NIST_KEY: [u8; 16] = [0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6, 0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf, 0x4f, 0x3c];
+const NIST_IV: [u8; 16] = [0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f];
+const NIST_PLAINTEXT: [u8; 18] = [0x6b, 0xc1, 0xbe, 0xe2, 0x2e, 0x40, 0x9f, 0x96, 0xe9, 0x3d, 0x7e, 0x11, 0x73, 0x93, 0x17, 0x2a, 0xae, 0x2d];
+const NIST_CIPHERTEXT: [u8; 18] = [0x3b, 0x79, 0x42, 0x4c, 0x9c, 0x0d, 0xd4, 0x36, 0xba, 0xce, 0x9e, 0x0e, 0xd4, 0x58, 0x6a, 0x4f, 0x32, 0xb9];
+
+// minecraft uses the shared secret as both the key and the iv
+const SECRET: [u8; 16] = NIST_IV;
+// Response { resp: "hello" }, framed
+const HELLO_FRAME: [u8; 8] = [0x07, 0x00, 0x05, 0x68, 0x65, 0x6c, 0x6c, 0x6f];
+const HELLO_FRAME_ENCRYPTED: [u8; 8] = [0x0d, 0x76, 0x2e, 0x5f, 0x31, 0x27, 0x08, 0x3f];
+
+#[test]
+fn encrypt_known_answer() {
+    let mut buf = NIST_PLAINTEXT;
+    let mut encrypt = Encryptor::new(&NIST_KEY, &NIST_IV);
+    // the cipher is a stream, so splitting the input shouldn't matter
+    let (first, second) = buf.split_at_mut(5);
+    encrypt.encrypt(first);
+    encrypt.encrypt(second);
+    assert_eq!(buf, NIST_CIPHERTEXT);
+}
+
+#[test]
+fn decrypt_known_answer() {
+    let mut buf = NIST_CIPHERTEXT;
+    let mut decrypt = Decryptor::new(&NIST_KEY, &NIST_IV);
+    for byte in buf.chunks_mut(1) {
+        decrypt.decrypt(byte);
+    }
+    assert_eq!(buf, NIST_PLAINTEXT);
+}
+
+#[tokio::test]
+async fn send_encrypts_after_enabling() {
+    let (server, mut client) = common::connect().await;
+    let (mut send, _recv) = mckerel_protocol::make_conn(server);
+
+    // buffered but unflushed packets have to go out unencrypted
+    send.write_packet(&Response { resp: "hello".to_owned() }).await.unwrap();
+    send.enable_encryption(&SECRET).await.unwrap();
+    send.send_packet(&Response { resp: "hello".to_owned() }).await.unwrap();
+
+    let mut wire = [0; 16];
+    client.read_exact(&mut wire).await.unwrap();
+    assert_eq!(wire[..8], HELLO_FRAME);
+    assert_eq!(wire[8..], HELLO_FRAME_ENCRYPTED);
+}
+
+#[tokio::test]
+async fn recv_decrypts_already_buffered_bytes() {
+    let (server, mut client) = common::connect().await;
+
+    // send both frames at once, so the encrypted one is already buffered
+    // by the time encryption gets turned on
+    let mut wire = HELLO_FRAME.to_vec();
+    wire.extend_from_slice(&HELLO_FRAME_ENCRYPTED);
+    client.write_all(&wire).await.unwrap();
+
+    let (_send, mut recv) = mckerel_protocol::make_conn(server);
+    let first = recv.read_packet().await.unwrap().read_all().await.unwrap();
+    recv.enable_encryption(&SECRET);
+    let second = recv.read_packet().await.unwrap().read_all().await.unwrap();
+    assert_eq!(first, HELLO_FRAME[1..]);
+    assert_eq!(second, HELLO_FRAME[1..]);
+}
+
+#[test]
+fn server_id_hash_known_answers() {
+    // the usual examples, hashing just the name
+    assert_eq!(server_id_hash("Notch", &[], &[]), "4ed1f46bbe04bc756bcb17c0c7ce3e4632f06a48");
+    assert_eq!(server_id_hash("jeb_", &[], &[]), "-7c9d5b0044c130109a5d7b5fb5c317c02b4e28c1");
+    assert_eq!(server_id_hash("simon", &[], &[]), "88e16a1019277b15d58faf0541e11910eb756f6");
+}
+
+#[test]
+fn server_id_hash_concatenates_parts() {
+    assert_eq!(server_id_hash("", b"Not", b"ch"), server_id_hash("Notch", &[], &[]));
+}
+
+#[test]
+fn server_key_decrypts_pkcs1v15() {
+    use rsa::{RsaPublicKey, Pkcs1v15Encrypt};
+    use rsa::pkcs8::DecodePublicKey;
+
+    let key = ServerKey::generate().unwrap();
+    // what the client does with the key from encryption request
+    let public = RsaPublicKey::from_public_key_der(key.public_key_der()).unwrap();
+    let encrypted = public.encrypt(&mut rand::rngs::OsRng, Pkcs1v15Encrypt, &SECRET).unwrap();
+    assert_eq!(encrypted.len(), 128);
+    assert_eq!(key.decrypt(&encrypted).unwrap(), SECRET);
+    assert!(key.decrypt(&[0; 128]).is_err());
+}
@@ -0,0 +1,58 @@
+use mckerel_protocol::de::{Deserialize, ByteReader, ErrorKind, PathSegment};
+use mckerel_protocol::serverbound::handshake;
+use mckerel_protocol::clientbound::play::{MapData, PlayerInfo};
+
+#[test]
+fn bad_handshake() {
+    // id, version 757, "a", port 25565, next state 3
+    let bytes = [0x00, 0xf5, 0x05, 1, b'a', 0x63, 0xdd, 3];
+    let err = handshake::Packet::deserialize(&mut ByteReader::new(&bytes)).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::BadEnumTag);
+    assert_eq!(err.offset(), 7);
+    assert_eq!(err.packet(), Some(("Handshake", 0x00)));
+    assert_eq!(err.path(), [PathSegment::Field("next_state")]);
+    assert_eq!(err.to_string(), "unknown enum tag at Handshake.next_state (packet 0x00, byte 7)");
+
+    let io_err = std::io::Error::from(err);
+    assert_eq!(io_err.kind(), std::io::ErrorKind::InvalidData);
+    assert!(io_err.to_string().contains("Handshake.next_state"));
+}
+
+#[test]
+fn bad_packet_id() {
+    let err = handshake::Packet::deserialize(&mut ByteReader::new(&[0x05])).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::BadPacketId(5));
+    assert_eq!(err.packet(), None);
+    assert_eq!(err.to_string(), "unknown packet id 0x05 (byte 0)");
+}
+
+#[test]
+fn paths_through_vecs_and_variants() {
+    // map 2, scale 0, not locked, tracking, one icon cut off after its kind
+    let bytes = [2, 0, 0, 1, 1, 1];
+    let err = MapData::deserialize(&mut ByteReader::new(&bytes)).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::Eof);
+    assert_eq!(err.offset(), 6);
+    assert_eq!(err.to_string(), "unexpected end of packet at icons[0].x (byte 6)");
+
+    // update latencies with one entry that's missing its ping
+    let mut bytes = vec![2, 1];
+    bytes.extend_from_slice(&1u128.to_be_bytes());
+    let err = PlayerInfo::deserialize(&mut ByteReader::new(&bytes)).err().unwrap();
+    assert_eq!(err.path(), [
+        PathSegment::Field("UpdateLatencies"),
+        PathSegment::Field("0"),
+        PathSegment::Index(0),
+        PathSegment::Field("ping"),
+    ]);
+}
+
+#[test]
+fn bad_utf8_offset() {
+    // id, version, a 3 byte string with a bad second byte
+    let bytes = [0x00, 1, 3, b'a', 0xff, b'b'];
+    let err = handshake::Packet::deserialize(&mut ByteReader::new(&bytes)).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::BadUtf8);
+    assert_eq!(err.offset(), 4);
+    assert_eq!(err.path(), [PathSegment::Field("address")]);
+}
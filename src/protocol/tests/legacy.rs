@@ -0,0 +1,111 @@
+use std::io;
+use mckerel_protocol::legacy::{self, LegacyPing, LegacyServerInfo};
+use mckerel_protocol::states::{Conn, HandshakeOrLegacyPing};
+use tokio::io::AsyncWriteExt;
+
+mod common;
+
+fn utf16(text: &str) -> Vec<u8> {
+    let mut data = (text.encode_utf16().count() as u16).to_be_bytes().to_vec();
+    data.extend(text.encode_utf16().flat_map(u16::to_be_bytes));
+    data
+}
+
+// what a 1.6 client sends
+fn ping_host(channel: &str, protocol: u8, hostname: &str, port: i32) -> Vec<u8> {
+    let mut payload = vec![protocol];
+    payload.extend(utf16(hostname));
+    payload.extend_from_slice(&port.to_be_bytes());
+    let mut data = vec![0xfe, 0x01, 0xfa];
+    data.extend(utf16(channel));
+    data.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+    data.extend(payload);
+    data
+}
+
+// the ping the server reads from a client that sends data and then waits
+async fn read_ping(data: &[u8]) -> io::Result<LegacyPing> {
+    let (server, mut client) = common::connect().await;
+    client.write_all(data).await.unwrap();
+    match Conn::new(server).read_handshake().await? {
+        HandshakeOrLegacyPing::LegacyPing(ping) => Ok(ping),
+        HandshakeOrLegacyPing::Handshake(_) => panic!("expected a legacy ping"),
+    }
+}
+
+fn info(motd: &str) -> LegacyServerInfo {
+    LegacyServerInfo {
+        protocol: 127,
+        version: "1.18.1".to_owned(),
+        motd: motd.to_owned(),
+        online: 3,
+        max: 20,
+    }
+}
+
+#[tokio::test]
+async fn pings() {
+    assert!(matches!(read_ping(&[0xfe]).await.unwrap(), LegacyPing::Beta));
+    assert!(matches!(read_ping(&[0xfe, 0x01]).await.unwrap(), LegacyPing::V1_4));
+    match read_ping(&ping_host("MC|PingHost", 78, "localhost", 25565)).await.unwrap() {
+        LegacyPing::V1_6 { protocol, hostname, port } => {
+            assert_eq!(protocol, 78);
+            assert_eq!(hostname, "localhost");
+            assert_eq!(port, 25565);
+        },
+        _ => panic!("expected a 1.6 ping"),
+    }
+}
+
+#[tokio::test]
+async fn truncated_ping_host() {
+    // the client stops partway through the hostname, so it gets the 1.4 response
+    let data = ping_host("MC|PingHost", 78, "localhost", 25565);
+    assert!(matches!(read_ping(&data[..data.len() - 8]).await.unwrap(), LegacyPing::V1_4));
+}
+
+#[tokio::test]
+async fn bad_ping_host() {
+    let err = read_ping(&ping_host("MC|Brand", 78, "localhost", 25565)).await.err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+    // the payload is too short for the hostname it says it has
+    let mut data = ping_host("MC|PingHost", 78, "", 25565);
+    let hostname_len = data.len() - 5;
+    data[hostname_len] = 5;
+    let err = read_ping(&data).await.err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn server_info() {
+    let mut expected = vec![0xff];
+    expected.extend(utf16("A server§3§20"));
+    assert_eq!(legacy::server_info_packet(&LegacyPing::Beta, &info("A server")), expected);
+
+    let mut expected = vec![0xff];
+    expected.extend(utf16(&["§1", "127", "1.18.1", "§cA server", "3", "20"].join("\0")));
+    assert_eq!(legacy::server_info_packet(&LegacyPing::V1_4, &info("§cA server")), expected);
+    let ping = LegacyPing::V1_6 { protocol: 78, hostname: "localhost".to_owned(), port: 25565 };
+    assert_eq!(legacy::server_info_packet(&ping, &info("§cA server")), expected);
+}
+
+#[test]
+fn beta_motd_without_codes() {
+    // beta uses § to separate the fields, so the codes get taken out entirely
+    let mut expected = vec![0xff];
+    expected.extend(utf16("Hi there§3§20"));
+    assert_eq!(legacy::server_info_packet(&LegacyPing::Beta, &info("§cHi §lthere§")), expected);
+}
+
+#[test]
+fn long_kick_message() {
+    let packet = legacy::kick_packet(&"a".repeat(70000));
+    assert_eq!(packet[1..3], [0xff, 0xff]);
+    assert_eq!(packet.len(), 3 + 0xffff * 2);
+
+    // a character that takes two units doesn't get split in half
+    let packet = legacy::kick_packet(&format!("{}\u{1f41f}", "a".repeat(0xfffe)));
+    assert_eq!(packet[1..3], [0xff, 0xfe]);
+    assert_eq!(packet.len(), 3 + 0xfffe * 2);
+}
@@ -0,0 +1,92 @@
+use mckerel_protocol::{Recv, Limits, FrameError};
+use mckerel_protocol::de::{self, ErrorKind, PathSegment};
+use mckerel_protocol::packets::serverbound;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+
+mod common;
+
+// the client side is a raw socket so it can send frames Send wouldn't
+async fn connect() -> (TcpStream, Recv) {
+    let (server, client) = common::connect().await;
+    (client, mckerel_protocol::make_conn(server).1)
+}
+
+fn frame_error(err: &std::io::Error) -> FrameError {
+    *err.get_ref().unwrap().downcast_ref::<FrameError>().unwrap()
+}
+
+fn de_error(err: &std::io::Error) -> de::Error {
+    err.get_ref().unwrap().downcast_ref::<de::Error>().unwrap().clone()
+}
+
+#[tokio::test]
+async fn frame_over_the_protocol_maximum() {
+    let (mut client, mut recv) = connect().await;
+    // 1 << 21, one more than a 3 byte VarInt can hold
+    client.write_all(&[0x80, 0x80, 0x80, 0x01]).await.unwrap();
+    let err = recv.read_packet().await.err().unwrap();
+    assert_eq!(frame_error(&err), FrameError::TooLong { len: 1 << 21, max: (1 << 21) - 1 });
+}
+
+#[tokio::test]
+async fn negative_frame_length() {
+    let (mut client, mut recv) = connect().await;
+    client.write_all(&[0xff, 0xff, 0xff, 0xff, 0x0f]).await.unwrap();
+    let err = recv.read_packet().await.err().unwrap();
+    assert_eq!(frame_error(&err), FrameError::NegativeLength);
+}
+
+#[tokio::test]
+async fn configured_limits() {
+    let (mut client, mut recv) = connect().await;
+    recv.set_limits(Limits { max_frame_length: 8, ..Limits::default() });
+    client.write_all(&[9]).await.unwrap();
+    let err = recv.read_packet().await.err().unwrap();
+    assert_eq!(frame_error(&err), FrameError::TooLong { len: 9, max: 8 });
+
+    let (mut client, mut recv) = connect().await;
+    recv.set_limits(Limits { max_data_length: 1000, ..Limits::default() });
+    recv.enable_compression(256);
+    // data length 1001, with nothing after it since it gets rejected first
+    client.write_all(&[3, 0xe9, 0x07]).await.unwrap();
+    let err = recv.read_packet().await.err().unwrap();
+    assert_eq!(frame_error(&err), FrameError::DataTooLong { len: 1001, max: 1000 });
+}
+
+#[tokio::test]
+async fn trailing_bytes() {
+    let (mut client, mut recv) = connect().await;
+    // login start for "jeb_", then one more byte
+    client.write_all(&[7, 0x00, 4, b'j', b'e', b'b', b'_', 0]).await.unwrap();
+    let err = recv.recv_packet::<serverbound::login::Packet>().await.err().unwrap();
+    let err = de_error(&err);
+    assert_eq!(err.kind(), ErrorKind::TrailingBytes(1));
+    assert_eq!(err.packet(), Some(("LoginStart", 0x00)));
+}
+
+#[tokio::test]
+async fn string_over_its_max_len() {
+    let (mut client, mut recv) = connect().await;
+    let mut frame = vec![19, 0x00, 17];
+    frame.extend_from_slice(b"abcdefghijklmnopq");
+    client.write_all(&frame).await.unwrap();
+    let err = recv.recv_packet::<serverbound::login::Packet>().await.err().unwrap();
+    let err = de_error(&err);
+    assert_eq!(err.kind(), ErrorKind::TooLong { len: 17, max: 16 });
+    assert_eq!(err.path(), [PathSegment::Field("name")]);
+    assert_eq!(err.offset(), 1);
+}
+
+#[test]
+fn strings_over_the_protocol_maximum() {
+    // a length prefix of 1 << 20 gets rejected before looking for that many bytes
+    let err = de::deserialize_all::<String>(&[0x80, 0x80, 0x40]).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::TooLong { len: 1 << 20, max: 32767 * 3 });
+
+    // 32768 ascii characters fit in the byte limit but are still one too many
+    let mut bytes = vec![0x80, 0x80, 0x02];
+    bytes.resize(3 + 32768, b'a');
+    let err = de::deserialize_all::<String>(&bytes).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::TooLong { len: 32768, max: 32767 });
+}
@@ -0,0 +1,182 @@
+use std::sync::Arc;
+use mckerel_protocol::{Send, Recv};
+use mckerel_protocol::auth::{Authenticator, HttpAuthenticator, MockAuthenticator};
+use mckerel_protocol::encryption::{ServerKey, server_id_hash};
+use mckerel_protocol::packets::{serverbound, clientbound, PROTOCOL_VERSION};
+use mckerel_protocol::states::login::{self, LoginConfig, OnlineMode, Profile};
+use mckerel_protocol::types::Uuid;
+use rsa::{RsaPublicKey, Pkcs1v15Encrypt};
+use rsa::pkcs8::DecodePublicKey;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+mod common;
+
+fn jeb() -> Profile {
+    Profile {
+        uuid: Uuid(0x853c80ef3c3749fdaa49938b674adae6),
+        username: "jeb_".to_owned(),
+        properties: Vec::new(),
+    }
+}
+
+// does what a vanilla client does, joining through the mock instead of mojang
+async fn client_login(send: &mut Send, recv: &mut Recv, mock: Option<&MockAuthenticator>, join_hash: Option<&str>) -> clientbound::login::LoginSuccess {
+    send.send_packet(&serverbound::login::LoginStart { name: "jeb_".to_owned() }).await.unwrap();
+    loop {
+        match recv.recv_packet().await.unwrap() {
+            clientbound::login::Packet::EncryptionRequest(request) => {
+                let public_key = RsaPublicKey::from_public_key_der(&request.public_key).unwrap();
+                let shared_secret: [u8; 16] = rand::random();
+                let server_hash = server_id_hash(&request.server_id, &shared_secret, &request.public_key);
+                mock.unwrap().join(join_hash.unwrap_or(&server_hash), jeb());
+
+                let mut rng = rand::rngs::OsRng;
+                send.send_packet(&serverbound::login::EncryptionResponse {
+                    shared_secret: public_key.encrypt(&mut rng, Pkcs1v15Encrypt, &shared_secret).unwrap(),
+                    verify_token: public_key.encrypt(&mut rng, Pkcs1v15Encrypt, &request.verify_token).unwrap(),
+                }).await.unwrap();
+                mckerel_protocol::enable_encryption(send, recv, &shared_secret).await.unwrap();
+            },
+            clientbound::login::Packet::SetCompression(compression) => {
+                mckerel_protocol::enable_compression(send, recv, compression.threshold as usize);
+            },
+            clientbound::login::Packet::LoginSuccess(success) => return success,
+            _ => panic!("unexpected packet"),
+        }
+    }
+}
+
+#[tokio::test]
+async fn offline_login() {
+    let (server_conn, (mut client_send, mut client_recv)) = common::login_conn(PROTOCOL_VERSION).await;
+    let server = tokio::spawn(async move {
+        login::login(server_conn, &LoginConfig::default()).await.map(|(_, profile)| profile)
+    });
+
+    let success = client_login(&mut client_send, &mut client_recv, None, None).await;
+    let profile = server.await.unwrap().unwrap();
+    assert_eq!(success.username, "jeb_");
+    assert_eq!(success.uuid, login::offline_uuid("jeb_"));
+    assert_eq!(profile.uuid, success.uuid);
+}
+
+#[tokio::test]
+async fn compression_threshold_too_big() {
+    let (server_conn, (_client_send, mut client_recv)) = common::login_conn(PROTOCOL_VERSION).await;
+    let config = LoginConfig { compression_threshold: Some(i32::MAX as usize + 1), online_mode: None };
+    let server = tokio::spawn(async move {
+        login::login(server_conn, &config).await.map(|(_, profile)| profile)
+    });
+    // it's the server's fault, so the client doesn't get to send anything
+    match client_recv.recv_packet().await.unwrap() {
+        clientbound::login::Packet::Disconnect(disconnect) => assert_eq!(disconnect.reason.to_plain(), "Internal server error"),
+        _ => panic!("expected a disconnect"),
+    }
+    let err = server.await.unwrap().err().unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[tokio::test]
+async fn online_login_with_mock() {
+    let (server_conn, (mut client_send, mut client_recv)) = common::login_conn(PROTOCOL_VERSION).await;
+    let mock = Arc::new(MockAuthenticator::new());
+    let config = LoginConfig {
+        compression_threshold: Some(64),
+        online_mode: Some(OnlineMode {
+            key: ServerKey::generate().unwrap(),
+            authenticator: Box::new(mock.clone()),
+        }),
+    };
+    let server = tokio::spawn(async move {
+        login::login(server_conn, &config).await.map(|(_, profile)| profile)
+    });
+
+    let success = client_login(&mut client_send, &mut client_recv, Some(&mock), None).await;
+    let profile = server.await.unwrap().unwrap();
+    assert_eq!(success.username, "jeb_");
+    assert_eq!(success.uuid, jeb().uuid);
+    assert_eq!(profile.uuid, jeb().uuid);
+}
+
+#[tokio::test]
+async fn online_login_rejects_wrong_hash() {
+    let (server_conn, (mut client_send, mut client_recv)) = common::login_conn(PROTOCOL_VERSION).await;
+    let mock = Arc::new(MockAuthenticator::new());
+    let config = LoginConfig {
+        compression_threshold: None,
+        online_mode: Some(OnlineMode {
+            key: ServerKey::generate().unwrap(),
+            authenticator: Box::new(mock.clone()),
+        }),
+    };
+    let server = tokio::spawn(async move {
+        login::login(server_conn, &config).await.map(|(_, profile)| profile)
+    });
+
+    let client = tokio::spawn(async move {
+        client_login(&mut client_send, &mut client_recv, Some(&mock), Some("not the hash")).await;
+    });
+    assert!(server.await.unwrap().is_err());
+    client.abort();
+}
+
+// a tiny session server that answers one hasJoined request
+async fn session_server(body: Option<&'static str>) -> (String, tokio::task::JoinHandle<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    let handle = tokio::spawn(async move {
+        let (mut conn, _) = listener.accept().await.unwrap();
+        let mut request = Vec::new();
+        while !request.ends_with(b"\r\n\r\n") {
+            request.push(conn.read_u8().await.unwrap());
+        }
+        let response = match body {
+            Some(body) => format!("HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}", body.len(), body),
+            None => "HTTP/1.1 204 No Content\r\nconnection: close\r\n\r\n".to_owned(),
+        };
+        conn.write_all(response.as_bytes()).await.unwrap();
+        String::from_utf8(request).unwrap()
+    });
+    (base_url, handle)
+}
+
+#[tokio::test]
+async fn http_authenticator_uses_base_url() {
+    let body = r#"{"id":"853c80ef3c3749fdaa49938b674adae6","name":"jeb_","properties":[{"name":"textures","value":"abc","signature":"def"}]}"#;
+    let (base_url, server) = session_server(Some(body)).await;
+
+    let profile = HttpAuthenticator::new(base_url).has_joined("jeb_", "-1234").await.unwrap().unwrap();
+    let request = server.await.unwrap();
+    assert!(request.starts_with("GET /session/minecraft/hasJoined?username=jeb_&serverId=-1234 "));
+    assert_eq!(profile.uuid, jeb().uuid);
+    assert_eq!(profile.username, "jeb_");
+    assert_eq!(profile.properties[0].name, "textures");
+    assert_eq!(profile.properties[0].signature.as_deref(), Some("def"));
+}
+
+#[tokio::test]
+async fn http_authenticator_not_joined() {
+    let (base_url, server) = session_server(None).await;
+    let profile = HttpAuthenticator::new(base_url).has_joined("jeb_", "-1234").await.unwrap();
+    server.await.unwrap();
+    assert!(profile.is_none());
+}
+
+#[tokio::test]
+async fn unsupported_version_gets_disconnected() {
+    let (server_conn, (mut client_send, mut client_recv)) = common::login_conn(47).await;
+    let server = tokio::spawn(async move {
+        login::login(server_conn, &LoginConfig::default()).await.map(|_| ())
+    });
+
+    client_send.send_packet(&serverbound::login::LoginStart { name: "jeb_".to_owned() }).await.unwrap();
+    match client_recv.recv_packet().await.unwrap() {
+        clientbound::login::Packet::Disconnect(disconnect) => {
+            assert_eq!(disconnect.reason.to_plain(), "Unsupported version, please use 1.16.5 to 1.18.1");
+        },
+        _ => panic!("expected a disconnect"),
+    }
+    drop((client_send, client_recv));
+    assert!(server.await.unwrap().is_err());
+}
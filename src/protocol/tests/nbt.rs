@@ -0,0 +1,211 @@
+use mckerel_protocol::de::{self, ErrorKind};
+use mckerel_protocol::ser::{Serialize, ByteWriter};
+use mckerel_protocol::nbt::{self, Nbt, Tag, Compound, FromNbt, FromNbtError, TagType};
+
+fn serialize<T: Serialize>(value: &T::Value) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    T::serialize(value, &mut ByteWriter::new(&mut bytes));
+    bytes
+}
+
+fn compound(entries: Vec<(&str, Tag)>) -> Compound {
+    entries.into_iter().map(|(name, tag)| (name.to_owned(), tag)).collect()
+}
+
+// hello_world.nbt from the original spec
+const HELLO_WORLD: &[u8] = &[
+    10, 0, 11, b'h', b'e', b'l', b'l', b'o', b' ', b'w', b'o', b'r', b'l', b'd',
+    8, 0, 4, b'n', b'a', b'm', b'e', 0, 9, b'B', b'a', b'n', b'a', b'n', b'r', b'a', b'm', b'a',
+    0,
+];
+
+#[test]
+fn binary_roundtrip() {
+    let nbt = de::deserialize_all::<Nbt>(HELLO_WORLD).unwrap();
+    assert_eq!(nbt.name, "hello world");
+    assert_eq!(nbt.root, compound(vec![("name", "Bananrama".into())]));
+    assert_eq!(serialize::<Nbt>(&nbt), HELLO_WORLD);
+
+    let root = compound(vec![
+        ("list", Tag::List(vec![Tag::Short(1), Tag::Short(-2)])),
+        ("bytes", Tag::ByteArray(vec![1, -1])),
+        ("longs", Tag::LongArray(vec![i64::MIN])),
+        ("nested", Tag::Compound(compound(vec![("pi", Tag::Double(1.5))]))),
+        ("empty", Tag::List(vec![])),
+    ]);
+    let bytes = serialize::<nbt::NetworkNbt>(&root);
+    // no name after the compound's type, straight into the byte array called "bytes"
+    assert_eq!(bytes[..4], [10, 7, 0, 5]);
+    assert_eq!(de::deserialize_all::<nbt::NetworkNbt>(&bytes).unwrap(), root);
+}
+
+#[test]
+fn modified_utf8() {
+    let string = "a\0b\u{1f41f}";
+    let bytes = serialize::<Tag>(&Tag::String(string.to_owned()));
+    // nul is two bytes and the fish is a surrogate pair of three bytes each
+    assert_eq!(bytes, [
+        8, 0, 10, b'a', 0xc0, 0x80, b'b', 0xed, 0xa0, 0xbd, 0xed, 0xb0, 0x9f,
+    ]);
+    assert_eq!(de::deserialize_all::<Tag>(&bytes).unwrap(), Tag::String(string.to_owned()));
+}
+
+#[test]
+fn optional_nbt() {
+    assert_eq!(serialize::<nbt::OptionalNbt>(&None), [0]);
+    assert_eq!(de::deserialize_all::<nbt::OptionalNbt>(&[0]).unwrap(), None);
+    let nbt = de::deserialize_all::<nbt::OptionalNbt>(HELLO_WORLD).unwrap();
+    assert_eq!(nbt.unwrap().name, "hello world");
+}
+
+#[test]
+fn too_deep() {
+    // lists of lists, one deeper than the maximum
+    let mut bytes = vec![9];
+    for _ in 0..=nbt::MAX_DEPTH {
+        bytes.extend_from_slice(&[9, 0, 0, 0, 1]);
+    }
+    let err = de::deserialize_all::<Tag>(&bytes).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::TooDeep);
+
+    let snbt = format!("{}{}", "[".repeat(nbt::MAX_DEPTH + 2), "]".repeat(nbt::MAX_DEPTH + 2));
+    assert_eq!(snbt.parse::<Tag>().err().unwrap().message, "nested too deep");
+}
+
+#[test]
+fn compressed() {
+    let nbt = de::deserialize_all::<Nbt>(HELLO_WORLD).unwrap();
+    assert_eq!(nbt::from_compressed(&nbt::to_gzip(&nbt)).unwrap(), nbt);
+    assert_eq!(nbt::from_compressed(&nbt::to_zlib(&nbt)).unwrap(), nbt);
+    assert_eq!(nbt::from_compressed(HELLO_WORLD).unwrap(), nbt);
+    assert!(nbt::from_compressed(&[0x1f, 0x8b, 0]).is_err());
+}
+
+#[test]
+fn snbt() {
+    let tag: Tag = r#"{ name: "jeb_", 'quoted key': 'it\'s', pos: [I; 1, 64, -3], health: 20.0f, flying: true, big: 3000000000 }"#
+        .parse().unwrap();
+    assert_eq!(tag, Tag::Compound(compound(vec![
+        ("name", "jeb_".into()),
+        ("quoted key", "it's".into()),
+        ("pos", Tag::IntArray(vec![1, 64, -3])),
+        ("health", Tag::Float(20.0)),
+        ("flying", Tag::Byte(1)),
+        ("big", Tag::String("3000000000".to_owned())),
+    ])));
+    assert_eq!(
+        tag.to_string(),
+        r#"{big:"3000000000",flying:1b,health:20.0f,name:"jeb_",pos:[I;1,64,-3],"quoted key":"it's"}"#,
+    );
+    assert_eq!(tag.to_string().parse::<Tag>().unwrap(), tag);
+
+    // 300 doesn't fit in a byte, so it's a string
+    assert_eq!("300b".parse::<Tag>().unwrap(), Tag::String("300b".to_owned()));
+    assert_eq!("[1L,2l]".parse::<Tag>().unwrap(), Tag::List(vec![Tag::Long(1), Tag::Long(2)]));
+
+    let scalar = |token: &str| token.parse::<Tag>().unwrap();
+    // only what vanilla's number patterns match is a number
+    assert_eq!(scalar("1."), Tag::Double(1.0));
+    assert_eq!(scalar("-.5e2"), Tag::Double(-50.0));
+    assert_eq!(scalar("1e5d"), Tag::Double(1e5));
+    assert_eq!(scalar("+2.5E-1F"), Tag::Float(0.25));
+    assert_eq!(scalar("-0"), Tag::Int(0));
+    for token in ["3000000000", "1e5", "1.5e", ".", "-infinity", "nanf", "infd", "01", "1.5b", "0x10", "1_000"] {
+        assert_eq!(scalar(token), Tag::String(token.to_owned()), "{} isn't a number", token);
+    }
+
+    let err = "[1, 2b]".parse::<Tag>().err().unwrap();
+    assert_eq!(err.to_string(), "list elements have to all be the same type at position 3");
+    let err = "{a:1} b".parse::<Tag>().err().unwrap();
+    assert_eq!(err.position, 6);
+    assert!("[B;1b,2]".parse::<Tag>().is_err());
+    assert!(r#"{a:"b}"#.parse::<Tag>().is_err());
+}
+
+#[derive(Debug, PartialEq)]
+struct Biome {
+    name: String,
+    id: i32,
+    temperature: Option<f32>,
+}
+
+impl FromNbt for Biome {
+    fn from_nbt(tag: &Tag) -> Result<Self, FromNbtError> {
+        let compound = Compound::from_nbt(tag)?;
+        Ok(Self {
+            name: nbt::get(&compound, "name")?,
+            id: nbt::get(&compound, "id")?,
+            temperature: nbt::get_optional(&compound, "temperature")?,
+        })
+    }
+}
+
+#[test]
+fn from_nbt() {
+    let tag: Tag = "{value:[{name:plains,id:1,temperature:0.8f},{name:void,id:2}]}".parse().unwrap();
+    let biomes: std::collections::BTreeMap<String, Vec<Biome>> = FromNbt::from_nbt(&tag).unwrap();
+    assert_eq!(biomes["value"], [
+        Biome { name: "plains".to_owned(), id: 1, temperature: Some(0.8) },
+        Biome { name: "void".to_owned(), id: 2, temperature: None },
+    ]);
+    assert_eq!(Vec::<i64>::from_nbt(&Tag::LongArray(vec![5])).unwrap(), [5]);
+
+    let tag: Tag = "{value:[{name:plains,id:1},{name:void,id:2b}]}".parse().unwrap();
+    let err = std::collections::BTreeMap::<String, Vec<Biome>>::from_nbt(&tag).err().unwrap();
+    assert_eq!(err, FromNbtError::WrongType { expected: TagType::Int, found: TagType::Byte, path: "value[1].id".to_owned() });
+    assert_eq!(err.to_string(), "expected int but found byte at `value[1].id`");
+
+    let tag: Tag = "{value:[{id:1}]}".parse().unwrap();
+    let err = std::collections::BTreeMap::<String, Vec<Biome>>::from_nbt(&tag).err().unwrap();
+    assert_eq!(err.to_string(), "`value[0].name` is missing");
+}
+
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+enum Effect {
+    Glowing,
+    Speed { amplifier: u8 },
+}
+
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+struct Player {
+    name: String,
+    level: i32,
+    flying: bool,
+    spawn: Option<(i32, i32, i32)>,
+    effects: Vec<Effect>,
+    heightmap: nbt::LongArray,
+    extra: Tag,
+}
+
+#[test]
+fn serde() {
+    let player = Player {
+        name: "jeb_".to_owned(),
+        level: 30,
+        flying: true,
+        spawn: None,
+        effects: vec![Effect::Speed { amplifier: 2 }],
+        heightmap: nbt::LongArray(vec![1, 2]),
+        extra: Tag::IntArray(vec![7]),
+    };
+    let tag = nbt::to_tag(&player).unwrap();
+    assert_eq!(
+        tag.to_string(),
+        r#"{effects:[{Speed:{amplifier:2s}}],extra:[I;7],flying:1b,heightmap:[L;1L,2L],level:30,name:"jeb_"}"#,
+    );
+
+    // arrays come back as lists when they're read into a Tag
+    let read: Player = nbt::from_tag(tag).unwrap();
+    assert_eq!(read.extra, Tag::List(vec![Tag::Int(7)]));
+    assert_eq!(read, Player { extra: Tag::List(vec![Tag::Int(7)]), ..player });
+
+    let tag: Tag = "{effects:[{Glowing:{}},{Speed:{amplifier:-1}}]}".parse().unwrap();
+    let err = nbt::from_tag::<std::collections::BTreeMap<String, Vec<Effect>>>(tag).err().unwrap();
+    assert_eq!(err.path(), "effects[1].Speed.amplifier");
+    assert_eq!(err.to_string(), "invalid value: integer `-1`, expected u8 at `effects[1].Speed.amplifier`");
+
+    let err = nbt::to_tag(&vec![Some(1), None]).err().unwrap();
+    assert_eq!(err.to_string(), "nbt has no null, a None can only be a field at `[1]`");
+    let err = nbt::to_compound(&[1, 2]).err().unwrap();
+    assert_eq!(err.to_string(), "expected a compound but found list");
+}
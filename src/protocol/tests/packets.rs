@@ -0,0 +1,182 @@
+use mckerel_protocol::{Packet, VarInt};
+use mckerel_protocol::de::{Deserialize, ByteReader};
+use mckerel_protocol::ser::{Serialize, ByteWriter};
+use mckerel_protocol::types::Uuid;
+use mckerel_protocol::serverbound::play::{InteractEntity, InteractKind, Hand};
+use mckerel_protocol::clientbound::play::{MapData, MapIcon, PlayerInfo, UpdateLatency};
+use mckerel_protocol::serverbound::handshake::{Handshake, HandshakeRef, HandshakeNextState};
+use mckerel_protocol::serverbound::login::LoginPluginResponseRef;
+
+// one field for each of the derive's field attributes
+#[derive(Packet)]
+#[packet(id = 0x7f)]
+struct FieldAttributes {
+    #[packet(len = "VarInt", with = "VarInt")]
+    counts: Vec<i32>,
+    #[packet(optional)]
+    name: Option<String>,
+    fixed: [u8; 3],
+    #[packet(rest)]
+    data: Vec<u8>,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x7f)]
+struct RestSlice<'de> {
+    #[packet(len = "VarInt")]
+    prefixed: &'de [u8],
+    #[packet(rest)]
+    data: &'de [u8],
+}
+
+fn write<P: Serialize<Value = P>>(packet: &P) -> Vec<u8> {
+    let mut written = Vec::new();
+    P::serialize(packet, &mut ByteWriter::new(&mut written));
+    written
+}
+
+fn read<P: for<'de> Deserialize<'de, Value = P>>(bytes: &[u8]) -> P {
+    let mut input = ByteReader::new(bytes);
+    let packet = P::deserialize(&mut input).unwrap();
+    assert!(input.done(), "didn't consume all of {:?}", bytes);
+    packet
+}
+
+#[test]
+fn interact_entity() {
+    let attack = InteractEntity { entity_id: 300, kind: InteractKind::Attack, sneaking: true };
+    let bytes = [0xac, 0x02, 1, 1];
+    assert_eq!(write(&attack), bytes);
+    assert!(matches!(read::<InteractEntity>(&bytes).kind, InteractKind::Attack));
+
+    let interact_at = InteractEntity {
+        entity_id: 1,
+        kind: InteractKind::InteractAt { target_x: 1.0, target_y: 0.0, target_z: -2.0, hand: Hand::Off },
+        sneaking: false,
+    };
+    let bytes = [1, 2, 0x3f, 0x80, 0, 0, 0, 0, 0, 0, 0xc0, 0, 0, 0, 1, 0];
+    assert_eq!(write(&interact_at), bytes);
+    match read::<InteractEntity>(&bytes).kind {
+        InteractKind::InteractAt { target_x, target_z, hand, .. } => {
+            assert_eq!((target_x, target_z, hand), (1.0, -2.0, Hand::Off));
+        },
+        _ => panic!("wrong kind"),
+    }
+
+    let mut input = ByteReader::new(&[1, 3, 0]);
+    assert!(InteractEntity::deserialize(&mut input).is_err());
+}
+
+#[test]
+fn map_data_conditional_fields() {
+    let empty = MapData {
+        map_id: 2, scale: 0, locked: false, tracking_position: false, icons: None,
+        columns: 0, rows: None, x: None, z: None, data: None,
+    };
+    let bytes = [2, 0, 0, 0, 0];
+    assert_eq!(write(&empty), bytes);
+    let read_empty = read::<MapData>(&bytes);
+    assert!(read_empty.icons.is_none() && read_empty.rows.is_none() && read_empty.data.is_none());
+
+    let full = MapData {
+        map_id: 2, scale: 1, locked: true, tracking_position: true,
+        icons: Some(vec![MapIcon { kind: 0, x: -1, z: 1, direction: 8, display_name: None }]),
+        columns: 1, rows: Some(2), x: Some(3), z: Some(4), data: Some(vec![5, 6]),
+    };
+    let bytes = [2, 1, 1, 1, 1, 0, 0xff, 1, 8, 0, 1, 2, 3, 4, 2, 5, 6];
+    assert_eq!(write(&full), bytes);
+    let read_full = read::<MapData>(&bytes);
+    assert_eq!(read_full.icons.unwrap()[0].x, -1);
+    assert_eq!((read_full.rows, read_full.data), (Some(2), Some(vec![5, 6])));
+}
+
+#[test]
+#[should_panic(expected = "has to be set")]
+fn map_data_missing_conditional_field() {
+    write(&MapData {
+        map_id: 2, scale: 0, locked: false, tracking_position: true, icons: None,
+        columns: 0, rows: None, x: None, z: None, data: None,
+    });
+}
+
+#[test]
+fn player_info_variants() {
+    let info = PlayerInfo::UpdateLatencies(vec![UpdateLatency { uuid: Uuid(1), ping: 2 }]);
+    let mut bytes = vec![2, 1];
+    bytes.extend_from_slice(&1u128.to_be_bytes());
+    bytes.push(2);
+    assert_eq!(write(&info), bytes);
+    match read::<PlayerInfo>(&bytes) {
+        PlayerInfo::UpdateLatencies(entries) => assert_eq!((entries[0].uuid, entries[0].ping), (Uuid(1), 2)),
+        _ => panic!("wrong action"),
+    }
+
+    let remove = PlayerInfo::RemovePlayers(vec![]);
+    assert_eq!(write(&remove), [4, 0]);
+    assert!(matches!(read::<PlayerInfo>(&[4, 0]), PlayerInfo::RemovePlayers(_)));
+}
+
+#[test]
+fn borrowed_handshake() {
+    let handshake = Handshake {
+        version: 757,
+        address: "localhost".to_owned(),
+        port: 25565,
+        next_state: HandshakeNextState::Login,
+    };
+    let bytes = write(&handshake);
+    let mut input = ByteReader::new(&bytes);
+    let borrowed = HandshakeRef::deserialize(&mut input).unwrap();
+    assert!(input.done());
+    // the address points into bytes instead of being copied
+    assert!(bytes.as_ptr_range().contains(&borrowed.address.as_ptr()));
+    assert_eq!(write(&borrowed), bytes);
+
+    let owned = borrowed.to_owned();
+    assert_eq!((owned.version, owned.address.as_str(), owned.port), (757, "localhost", 25565));
+    assert_eq!(owned.next_state, HandshakeNextState::Login);
+}
+
+#[test]
+fn borrowed_plugin_response() {
+    let bytes = [1, 1, 0xca, 0xfe];
+    let response = LoginPluginResponseRef::deserialize(&mut ByteReader::new(&bytes)).unwrap();
+    assert_eq!((response.message_id, response.successful, response.data), (1, true, &bytes[2..]));
+    assert_eq!(response.to_owned().data, [0xca, 0xfe]);
+}
+
+#[test]
+fn field_attributes() {
+    let packet = FieldAttributes {
+        counts: vec![1, 300],
+        name: Some("hi".to_owned()),
+        fixed: [7, 8, 9],
+        data: vec![0xca, 0xfe],
+    };
+    let bytes = [2, 1, 0xac, 0x02, 1, 2, b'h', b'i', 7, 8, 9, 0xca, 0xfe];
+    assert_eq!(write(&packet), bytes);
+    let read_packet = read::<FieldAttributes>(&bytes);
+    assert_eq!(read_packet.counts, [1, 300]);
+    assert_eq!(read_packet.name.as_deref(), Some("hi"));
+    assert_eq!((read_packet.fixed, read_packet.data), ([7, 8, 9], vec![0xca, 0xfe]));
+
+    // empty, absent and nothing left over
+    let packet = FieldAttributes { counts: Vec::new(), name: None, fixed: [0; 3], data: Vec::new() };
+    let bytes = [0, 0, 0, 0, 0];
+    assert_eq!(write(&packet), bytes);
+    let read_packet = read::<FieldAttributes>(&bytes);
+    assert!(read_packet.counts.is_empty() && read_packet.name.is_none() && read_packet.data.is_empty());
+
+    // the fixed array has to be all there
+    assert!(FieldAttributes::deserialize(&mut ByteReader::new(&[0, 0, 7, 8])).is_err());
+}
+
+#[test]
+fn rest_slice() {
+    let bytes = [2, 1, 2, 3, 4, 5];
+    let mut input = ByteReader::new(&bytes);
+    let packet = RestSlice::deserialize(&mut input).unwrap();
+    assert!(input.done());
+    assert_eq!((packet.prefixed, packet.data), (&bytes[1..3], &bytes[3..]));
+    assert_eq!(write(&packet), bytes);
+}
@@ -0,0 +1,124 @@
+use mckerel_protocol::nbt::Tag;
+use mckerel_protocol::packets::{serverbound, clientbound};
+use mckerel_protocol::states::play::{self, JoinConfig, DimensionType, Biome};
+use mckerel_protocol::types::Position;
+
+mod common;
+
+#[test]
+fn dimension_codec() {
+    let dimension = DimensionType { min_y: -64, height: 384, ..DimensionType::default() };
+    let codec = play::dimension_codec(&[("mckerel:tall", &dimension)], &[("minecraft:plains", &Biome::default())]);
+    let dimension_types = match &codec.root["minecraft:dimension_type"] {
+        Tag::Compound(registry) => registry,
+        _ => panic!("the registry isn't a compound"),
+    };
+    assert_eq!(dimension_types["type"], Tag::from("minecraft:dimension_type"));
+    let entry = match &dimension_types["value"] {
+        Tag::List(entries) => match &entries[0] {
+            Tag::Compound(entry) => entry,
+            _ => panic!("the entry isn't a compound"),
+        },
+        _ => panic!("the entries aren't a list"),
+    };
+    assert_eq!(entry["name"], Tag::from("mckerel:tall"));
+    assert_eq!(entry["id"], Tag::Int(0));
+    let element = match &entry["element"] {
+        Tag::Compound(element) => element,
+        _ => panic!("the element isn't a compound"),
+    };
+    assert_eq!(element["min_y"], Tag::Int(-64));
+    assert_eq!(element["has_skylight"], Tag::Byte(1));
+    assert_eq!(element["coordinate_scale"], Tag::Double(1.0));
+    assert!(!element.contains_key("fixed_time"));
+    assert!(codec.root.contains_key("minecraft:worldgen/biome"));
+}
+
+#[test]
+fn empty_chunk() {
+    let dimension = DimensionType { min_y: -64, height: 384, ..DimensionType::default() };
+    let chunk = play::empty_chunk(3, -2, &dimension);
+    assert_eq!((chunk.x, chunk.z), (3, -2));
+    // a block count and two single value containers for each of the 24 sections
+    assert_eq!(chunk.data.len(), 24 * 8);
+    assert_eq!(chunk.data[..8], [0, 0, 0, 0, 0, 0, 0, 0]);
+}
+
+#[tokio::test]
+async fn join() {
+    let (mut server, (_client_send, mut client_recv)) = common::play_conn(757).await;
+    let config = JoinConfig { view_distance: 2, spawn: (-20.5, 100.0, 40.0), ..JoinConfig::default() };
+    let expected = config.clone();
+    let server = tokio::spawn(async move { play::join(&mut server, &config).await });
+
+    let mut chunks = Vec::new();
+    let mut packets = Vec::new();
+    loop {
+        match client_recv.recv_packet().await.unwrap() {
+            clientbound::play::Packet::ChunkData(chunk) => chunks.push((chunk.x, chunk.z)),
+            clientbound::play::Packet::PlayerPositionAndLook(position) => {
+                assert_eq!((position.x, position.y, position.z), expected.spawn);
+                break;
+            },
+            packet => packets.push(packet),
+        }
+    }
+    server.await.unwrap().unwrap();
+
+    match &packets[..] {
+        [
+            clientbound::play::Packet::JoinGame(join),
+            clientbound::play::Packet::PlayerAbilities(abilities),
+            clientbound::play::Packet::HeldItemChange(_),
+            clientbound::play::Packet::UpdateViewPosition(view),
+            clientbound::play::Packet::SpawnPosition(spawn),
+        ] => {
+            assert_eq!(join.entity_id, expected.entity_id);
+            assert_eq!(join.world_name, "mckerel:void");
+            assert_eq!(join.view_distance, 2);
+            // creative and flying
+            assert_eq!(abilities.flags, 0x0f);
+            assert_eq!((view.chunk_x, view.chunk_z), (-2, 2));
+            assert_eq!(spawn.location, Position::new(-21, 100, 40));
+        },
+        _ => panic!("wrong packets"),
+    }
+    // everything within the view distance of the spawn chunk
+    assert_eq!(chunks.len(), 25);
+    assert!(chunks.contains(&(-4, 0)) && chunks.contains(&(0, 4)));
+}
+
+#[tokio::test]
+async fn old_versions_get_kicked() {
+    // 1.16.5 and 1.17.1 can't be sent chunks, so they'd never get past the loading screen
+    for version in [754, 756] {
+        let (server, (_client_send, mut client_recv)) = common::play_conn(version).await;
+        let server = tokio::spawn(async move { play::void_world(server, &JoinConfig::default()).await });
+        let data = client_recv.read_packet().await.unwrap().read_all().await.unwrap();
+        // nothing else gets sent first, just play disconnect
+        let disconnect = if version == 754 { 0x19 } else { 0x1a };
+        assert_eq!(data[0], disconnect);
+        assert_eq!(&data[2..], br#"{"text":"This server needs Minecraft 1.18.1"}"#);
+        let err = server.await.unwrap().err().unwrap();
+        assert_eq!(err.to_string(), "kicked: This server needs Minecraft 1.18.1");
+    }
+}
+
+#[tokio::test]
+async fn void_world_keep_alive() {
+    let (server, (mut client_send, mut client_recv)) = common::play_conn(757).await;
+    let server = tokio::spawn(async move { play::void_world(server, &JoinConfig::default()).await });
+
+    // the first keep alive comes straight after joining
+    let id = loop {
+        if let clientbound::play::Packet::KeepAlive(keep_alive) = client_recv.recv_packet().await.unwrap() {
+            break keep_alive.0;
+        }
+    };
+    // packets without types get skipped, this is teleport confirm
+    client_send.write_packet_bytes(&[0x00, 0x00]).await.unwrap();
+    client_send.send_packet(&serverbound::play::KeepAlive(id)).await.unwrap();
+    // leaving isn't an error
+    drop((client_send, client_recv));
+    server.await.unwrap().unwrap();
+}
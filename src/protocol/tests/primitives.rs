@@ -0,0 +1,127 @@
+use std::fmt::Debug;
+use mckerel_protocol::{VarInt, VarLong};
+use mckerel_protocol::de::{Deserialize, ByteReader};
+use mckerel_protocol::ser::{Serialize, ByteWriter};
+use mckerel_protocol::types::{Uuid, Position, Angle};
+
+// checks both directions against known bytes
+fn check<T, V>(value: V, bytes: &[u8])
+where
+    T: Serialize<Value = V> + for<'de> Deserialize<'de, Value = V>,
+    V: PartialEq + Debug,
+{
+    let mut written = Vec::new();
+    T::serialize(&value, &mut ByteWriter::new(&mut written));
+    assert_eq!(written, bytes, "serializing {:?}", value);
+
+    let mut input = ByteReader::new(bytes);
+    assert_eq!(T::deserialize(&mut input).unwrap(), value);
+    assert!(input.done(), "{:?} didn't consume all of {:?}", value, bytes);
+}
+
+fn round_trip<T, V>(value: V)
+where
+    T: Serialize<Value = V> + for<'de> Deserialize<'de, Value = V>,
+    V: PartialEq + Debug,
+{
+    let mut written = Vec::new();
+    T::serialize(&value, &mut ByteWriter::new(&mut written));
+    let mut input = ByteReader::new(&written);
+    assert_eq!(T::deserialize(&mut input).unwrap(), value);
+    assert!(input.done());
+}
+
+#[test]
+fn integers_are_big_endian() {
+    check::<u8, _>(0xab, &[0xab]);
+    check::<i8, _>(-2, &[0xfe]);
+    check::<u16, _>(25565, &[0x63, 0xdd]);
+    check::<i16, _>(-32768, &[0x80, 0x00]);
+    check::<u32, _>(0x01020304, &[0x01, 0x02, 0x03, 0x04]);
+    check::<i32, _>(-1, &[0xff, 0xff, 0xff, 0xff]);
+    check::<u64, _>(0x0102030405060708, &[0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08]);
+    check::<i64, _>(i64::MIN, &[0x80, 0, 0, 0, 0, 0, 0, 0]);
+}
+
+#[test]
+fn floats() {
+    check::<f32, _>(1.0, &[0x3f, 0x80, 0x00, 0x00]);
+    check::<f32, _>(-0.15625, &[0xbe, 0x20, 0x00, 0x00]);
+    check::<f64, _>(-2.5, &[0xc0, 0x04, 0, 0, 0, 0, 0, 0]);
+    check::<f64, _>(1.0 / 3.0, &[0x3f, 0xd5, 0x55, 0x55, 0x55, 0x55, 0x55, 0x55]);
+    round_trip::<f32, _>(f32::MAX);
+    round_trip::<f64, _>(f64::MIN_POSITIVE);
+    round_trip::<f64, _>(f64::NEG_INFINITY);
+}
+
+#[test]
+fn bools() {
+    check::<bool, _>(false, &[0x00]);
+    check::<bool, _>(true, &[0x01]);
+    assert!(bool::deserialize(&mut ByteReader::new(&[0x02])).is_err());
+}
+
+#[test]
+fn varints() {
+    check::<VarInt, _>(0, &[0x00]);
+    check::<VarInt, _>(1, &[0x01]);
+    check::<VarInt, _>(127, &[0x7f]);
+    check::<VarInt, _>(128, &[0x80, 0x01]);
+    check::<VarInt, _>(255, &[0xff, 0x01]);
+    check::<VarInt, _>(25565, &[0xdd, 0xc7, 0x01]);
+    check::<VarInt, _>(2097151, &[0xff, 0xff, 0x7f]);
+    check::<VarInt, _>(i32::MAX, &[0xff, 0xff, 0xff, 0xff, 0x07]);
+    check::<VarInt, _>(-1, &[0xff, 0xff, 0xff, 0xff, 0x0f]);
+    check::<VarInt, _>(i32::MIN, &[0x80, 0x80, 0x80, 0x80, 0x08]);
+    // too long
+    assert!(VarInt::deserialize(&mut ByteReader::new(&[0xff, 0xff, 0xff, 0xff, 0xff, 0x01])).is_err());
+}
+
+#[test]
+fn varlongs() {
+    check::<VarLong, _>(0, &[0x00]);
+    check::<VarLong, _>(2147483647, &[0xff, 0xff, 0xff, 0xff, 0x07]);
+    check::<VarLong, _>(i64::MAX, &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f]);
+    check::<VarLong, _>(-1, &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01]);
+    check::<VarLong, _>(i64::MIN, &[0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x01]);
+}
+
+#[test]
+fn strings() {
+    check::<String, _>("hi".to_owned(), &[0x02, b'h', b'i']);
+    check::<String, _>("§".to_owned(), &[0x02, 0xc2, 0xa7]);
+    assert!(String::deserialize(&mut ByteReader::new(&[0x02, 0xc2])).is_err());
+    assert!(String::deserialize(&mut ByteReader::new(&[0x01, 0xff])).is_err());
+}
+
+#[test]
+fn uuids() {
+    let notch = Uuid(0x069a79f444e94726a5befca90e38aaf5);
+    check::<Uuid, _>(notch, &[
+        0x06, 0x9a, 0x79, 0xf4, 0x44, 0xe9, 0x47, 0x26,
+        0xa5, 0xbe, 0xfc, 0xa9, 0x0e, 0x38, 0xaa, 0xf5,
+    ]);
+    assert_eq!(notch.to_string(), "069a79f4-44e9-4726-a5be-fca90e38aaf5");
+    assert_eq!(Uuid(1).to_string(), "00000000-0000-0000-0000-000000000001");
+}
+
+#[test]
+fn positions() {
+    // the example from wiki.vg
+    let pos = Position::new(18357644, 831, -20882616);
+    assert_eq!(pos.pack(), 0b0100011000000111011000110010110000010101101101001000001100111111);
+    check::<Position, _>(pos, &[0x46, 0x07, 0x63, 0x2c, 0x15, 0xb4, 0x83, 0x3f]);
+
+    check::<Position, _>(Position::new(-1, -1, -1), &[0xff; 8]);
+    round_trip::<Position, _>(Position::new(-33554432, -2048, 33554431));
+    round_trip::<Position, _>(Position::new(0, 2047, 0));
+}
+
+#[test]
+fn angles() {
+    check::<Angle, _>(Angle(64), &[0x40]);
+    assert_eq!(Angle::from_degrees(90.0), Angle(64));
+    assert_eq!(Angle::from_degrees(-90.0), Angle(192));
+    assert_eq!(Angle::from_degrees(359.9), Angle(0));
+    assert_eq!(Angle(128).to_degrees(), 180.0);
+}
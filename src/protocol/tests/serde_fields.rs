@@ -0,0 +1,68 @@
+#![cfg(feature = "serde_fields")]
+use std::collections::BTreeMap;
+use mckerel_protocol::Packet;
+use mckerel_protocol::de::{Deserialize, ByteReader, ErrorKind, PathSegment};
+use mckerel_protocol::ser::{Serialize, ByteWriter};
+use mckerel_protocol::serde_fields::{Json, Nbt};
+
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+struct Biome {
+    name: String,
+    id: i32,
+}
+
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+struct Chat<'a> {
+    text: &'a str,
+}
+
+#[derive(Debug, PartialEq, Packet)]
+#[packet(id = 0x20)]
+pub struct Registry<'de> {
+    biomes: Nbt<BTreeMap<String, Vec<Biome>>>,
+    motd: Json<Chat<'de>>,
+}
+
+#[test]
+fn roundtrip() {
+    let mut biomes = BTreeMap::new();
+    biomes.insert("value".to_owned(), vec![Biome { name: "minecraft:plains".to_owned(), id: 1 }]);
+    let registry = Registry { biomes: Nbt::new(biomes).unwrap(), motd: Json::new(Chat { text: "hi" }).unwrap() };
+
+    let mut bytes = Vec::new();
+    Registry::serialize(&registry, &mut ByteWriter::new(&mut bytes));
+    assert!(bytes.ends_with(br#"{"text":"hi"}"#));
+    let read = Registry::deserialize(&mut ByteReader::new(&bytes)).unwrap();
+    assert_eq!(read, registry);
+    assert_eq!(read.motd.text, "hi");
+    assert_eq!(read.biomes["value"][0].id, 1);
+}
+
+#[test]
+fn unencodable_values() {
+    // json keys have to be strings
+    let mut by_position = BTreeMap::new();
+    by_position.insert((1, 2), "a");
+    assert!(Json::new(by_position).is_err());
+    // the root of nbt has to be a compound
+    let err = Nbt::new(vec![1, 2]).err().unwrap();
+    assert_eq!(err.message(), "expected a compound but found list");
+}
+
+#[test]
+fn mismatched_fields() {
+    let mut bytes = vec![10, 0, 0, 0];
+    bytes.push(7);
+    bytes.extend_from_slice(br#"{"a":1}"#);
+    let err = Registry::deserialize(&mut ByteReader::new(&bytes)).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::BadJson);
+    assert_eq!(err.path(), [PathSegment::Field("motd")]);
+    assert_eq!(err.offset(), 4);
+    assert_eq!(err.detail(), Some("missing field `text` at line 1 column 7"));
+
+    // a compound with an int where the list of biomes should be
+    let bytes = [10, 0, 0, 3, 0, 5, b'v', b'a', b'l', b'u', b'e', 0, 0, 0, 1, 0, 2, b'{', b'}'];
+    let err = Registry::deserialize(&mut ByteReader::new(&bytes)).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::BadNbt);
+    assert_eq!(err.to_string(), "nbt doesn't match its type at biomes (byte 0): invalid type: integer `1`, expected a sequence at `value`");
+}
@@ -0,0 +1,68 @@
+use std::io;
+use mckerel_protocol::packets::{serverbound, clientbound};
+use mckerel_protocol::states::status::{self, ServerStatus, Version, PlayerSample, Favicon};
+use mckerel_protocol::types::Uuid;
+
+mod common;
+
+// just the signature and the start of the IHDR chunk, which is all that gets checked
+fn png(width: u32, height: u32) -> Vec<u8> {
+    let mut png = vec![0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a, 0, 0, 0, 13];
+    png.extend_from_slice(b"IHDR");
+    png.extend_from_slice(&width.to_be_bytes());
+    png.extend_from_slice(&height.to_be_bytes());
+    png
+}
+
+#[test]
+fn status_json() {
+    let mut status = ServerStatus::new("A server", 20, 1);
+    status.version = Version { name: "1.18.1".to_owned(), protocol: 757 };
+    status.players.sample.push(PlayerSample::new("jeb_", Uuid(0x853c80ef3c3749fdaa49938b674adae6)));
+    status.favicon = Some(Favicon::from_png(&png(64, 64)).unwrap());
+    assert_eq!(status.to_json(), concat!(
+        r#"{"version":{"name":"1.18.1","protocol":757},"#,
+        r#""players":{"max":20,"online":1,"sample":[{"name":"jeb_","id":"853c80ef-3c37-49fd-aa49-938b674adae6"}]},"#,
+        r#""description":{"text":"A server"},"#,
+        r#""favicon":"data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAEAAAABA"}"#,
+    ));
+    // no sample or favicon means leaving them out
+    let status = ServerStatus::new("A server", 20, 1);
+    assert!(!status.to_json().contains("sample") && !status.to_json().contains("favicon"));
+}
+
+#[test]
+fn bad_favicons() {
+    let err = Favicon::from_png(b"GIF89a, which is not a png at all").err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(err.to_string(), "favicon isn't a png");
+
+    let err = Favicon::from_png(&png(128, 128)).err().unwrap();
+    assert_eq!(err.to_string(), "favicon isn't 64x64");
+    let err = Favicon::from_png(&png(64, 32)).err().unwrap();
+    assert_eq!(err.to_string(), "favicon isn't 64x64");
+}
+
+#[tokio::test]
+async fn request_and_ping() {
+    let (server, (mut client_send, mut client_recv)) = common::status_conn(754).await;
+    let status = ServerStatus::new("A server", 20, 1);
+    let expected = status.to_json_with_protocol(754);
+    let server = tokio::spawn(async move { status::respond(server, &status).await });
+
+    client_send.send_packet(&serverbound::status::Request).await.unwrap();
+    match client_recv.recv_packet().await.unwrap() {
+        // a supported client gets its own protocol back
+        clientbound::status::Packet::Response(response) => assert_eq!(response.resp, expected),
+        _ => panic!("expected a response"),
+    }
+    client_send.send_packet(&serverbound::status::Ping(42)).await.unwrap();
+    match client_recv.recv_packet().await.unwrap() {
+        clientbound::status::Packet::Pong(pong) => assert_eq!(pong.0, 42),
+        _ => panic!("expected a pong"),
+    }
+    // the server hangs up after the pong
+    let err = client_recv.read_packet().await.err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    server.await.unwrap().unwrap();
+}
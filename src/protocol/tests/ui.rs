@@ -0,0 +1,7 @@
+// compile tests for the derive, run with TRYBUILD=overwrite to update the expected errors
+#[test]
+fn ui() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/ui/pass/*.rs");
+    t.compile_fail("tests/ui/fail/*.rs");
+}
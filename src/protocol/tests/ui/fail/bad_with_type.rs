@@ -0,0 +1,10 @@
+use mckerel_protocol::Packet;
+
+#[derive(Packet)]
+#[packet(id = 0)]
+pub struct BadType {
+    #[packet(with = "not a type")]
+    pub count: i32,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use mckerel_protocol::Packet;
+
+#[derive(Packet)]
+#[packet(id = 0)]
+pub struct Both {
+    #[packet(len = "mckerel_protocol::VarInt", rest)]
+    pub data: Vec<u8>,
+}
+
+fn main() {}
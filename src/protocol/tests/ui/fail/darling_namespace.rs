@@ -0,0 +1,11 @@
+use mckerel_protocol::Packet;
+
+// field attributes live under packet, not darling
+#[derive(Packet)]
+#[packet(id = 0)]
+pub struct Old {
+    #[darling(with = "mckerel_protocol::VarInt")]
+    pub count: i32,
+}
+
+fn main() {}
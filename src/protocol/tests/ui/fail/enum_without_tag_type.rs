@@ -0,0 +1,10 @@
+use mckerel_protocol::Packet;
+
+#[derive(Packet)]
+#[packet(id = 0)]
+pub enum Untagged {
+    A,
+    B,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use mckerel_protocol::Packet;
+
+#[derive(Packet)]
+#[packet(id = 0)]
+pub struct NotVec {
+    #[packet(len = "mckerel_protocol::VarInt")]
+    pub data: String,
+}
+
+fn main() {}
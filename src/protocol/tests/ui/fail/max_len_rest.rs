@@ -0,0 +1,10 @@
+use mckerel_protocol::Packet;
+
+#[derive(Packet)]
+#[packet(id = 0)]
+pub struct RestMaxLen {
+    #[packet(rest, max_len = 16)]
+    pub data: Vec<u8>,
+}
+
+fn main() {}
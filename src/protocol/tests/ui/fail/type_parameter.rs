@@ -0,0 +1,9 @@
+use mckerel_protocol::Packet;
+
+#[derive(Packet)]
+#[packet(id = 0)]
+pub struct Generic<T> {
+    pub value: T,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use mckerel_protocol::Packet;
+
+#[derive(Packet)]
+#[packet(id = 0)]
+pub union Union {
+    a: u8,
+    b: i8,
+}
+
+fn main() {}
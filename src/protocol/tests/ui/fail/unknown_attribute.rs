@@ -0,0 +1,10 @@
+use mckerel_protocol::Packet;
+
+#[derive(Packet)]
+#[packet(id = 0)]
+pub struct Typo {
+    #[packet(wiht = "mckerel_protocol::VarInt")]
+    pub count: i32,
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use mckerel_protocol::Packet;
+
+#[derive(Packet)]
+#[packet(id = 0, tag_type = "u8")]
+pub enum MissingTag {
+    #[packet(tag = 0)]
+    A,
+    B,
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use mckerel_protocol::Packet;
+
+#[derive(Packet)]
+#[packet(id = 0)]
+pub struct NotOption {
+    pub present: bool,
+    #[packet(when = "*present")]
+    pub value: u8,
+}
+
+fn main() {}
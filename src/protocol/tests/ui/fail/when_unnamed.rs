@@ -0,0 +1,7 @@
+use mckerel_protocol::Packet;
+
+#[derive(Packet)]
+#[packet(id = 0)]
+pub struct Tuple(bool, #[packet(when = "true")] Option<u8>);
+
+fn main() {}
@@ -0,0 +1,49 @@
+use mckerel_protocol::{Packet, VarInt};
+use mckerel_protocol::de::{Deserialize, ByteReader};
+
+#[derive(Packet)]
+#[packet(id = 0x10)]
+pub struct Message {
+    pub channel: String,
+    #[packet(len = "VarInt")]
+    pub data: Vec<u8>,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x10, owned = "Message")]
+pub struct MessageRef<'de> {
+    pub channel: &'de str,
+    #[packet(len = "VarInt")]
+    pub data: &'de [u8],
+}
+
+#[derive(Packet)]
+#[packet(tag_type = "u8")]
+pub enum Chunk {
+    #[packet(tag = 0)]
+    Empty,
+    #[packet(tag = 1)]
+    Full(#[packet(rest)] Vec<u8>),
+}
+
+#[derive(Packet)]
+#[packet(tag_type = "u8", owned = "Chunk")]
+pub enum ChunkRef<'de> {
+    #[packet(tag = 0)]
+    Empty,
+    #[packet(tag = 1)]
+    Full(#[packet(rest)] &'de [u8]),
+}
+
+fn main() {
+    let bytes = [2, b'h', b'i', 1, 7];
+    let message = MessageRef::deserialize(&mut ByteReader::new(&bytes)).unwrap();
+    assert_eq!((message.channel, message.data), ("hi", &[7][..]));
+    let owned: Message = message.to_owned();
+    assert_eq!((owned.channel.as_str(), owned.data), ("hi", vec![7]));
+
+    match ChunkRef::deserialize(&mut ByteReader::new(&[1, 5, 6])).unwrap().to_owned() {
+        Chunk::Full(data) => assert_eq!(data, [5, 6]),
+        Chunk::Empty => panic!("wrong variant"),
+    }
+}
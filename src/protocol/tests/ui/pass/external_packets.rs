@@ -0,0 +1,46 @@
+use mckerel_protocol::{enum_impl, packets_impl, Packet, VarInt};
+use mckerel_protocol::de::{Deserialize, ByteReader};
+use mckerel_protocol::ser::{Serialize, ByteWriter};
+
+enum_impl!(VarInt Mode {
+    Off = 0,
+    On = 1
+});
+
+#[derive(Packet)]
+#[packet(id = 0x40)]
+pub struct Custom {
+    #[packet(with = "VarInt")]
+    pub count: i32,
+    pub mode: Mode,
+    #[packet(when = "*mode == Mode::On", len = "VarInt")]
+    pub data: Option<Vec<u8>>,
+}
+
+#[derive(Packet)]
+#[packet(id = 0x41, tag_type = "u8")]
+pub enum Action {
+    #[packet(tag = 0)]
+    Start,
+    #[packet(tag = 1)]
+    Stop { reason: String },
+}
+
+packets_impl!(CustomPacket {
+    Custom,
+    Action
+});
+
+fn main() {
+    let mut written = Vec::new();
+    let packet = CustomPacket::from(Custom { count: 300, mode: Mode::On, data: Some(vec![7]) });
+    CustomPacket::serialize(&packet, &mut ByteWriter::new(&mut written));
+    // with = "VarInt" has to take effect, otherwise count would be 4 bytes
+    assert_eq!(written, [0x40, 0xac, 0x02, 1, 1, 7]);
+
+    let mut input = ByteReader::new(&[0x41, 1, 2, b'h', b'i']);
+    match CustomPacket::deserialize(&mut input).unwrap() {
+        CustomPacket::Action(Action::Stop { reason }) => assert_eq!(reason, "hi"),
+        _ => panic!("wrong packet"),
+    }
+}
@@ -0,0 +1,26 @@
+mod reexport {
+    pub use mckerel_protocol as proto;
+}
+
+use reexport::proto::{enum_impl, Packet};
+use reexport::proto::ser::{Serialize, ByteWriter};
+
+enum_impl!(#[packet(crate = "reexport::proto")] u8 Flag {
+    No = 0,
+    Yes = 1
+});
+
+#[derive(Packet)]
+#[packet(id = 1, crate = "reexport::proto")]
+pub struct Renamed {
+    pub flag: Flag,
+    #[packet(with = "reexport::proto::VarInt")]
+    pub value: i32,
+}
+
+fn main() {
+    let mut written = Vec::new();
+    Renamed::serialize(&Renamed { flag: Flag::Yes, value: 1 }, &mut ByteWriter::new(&mut written));
+    assert_eq!(written, [1, 1]);
+    assert_eq!(<Renamed as reexport::proto::Packet>::ID, 1);
+}
@@ -0,0 +1,105 @@
+use mckerel_protocol::VarInt;
+use mckerel_protocol::de::{Deserialize, ByteReader, ErrorKind};
+use mckerel_protocol::ser::{Serialize, ByteWriter};
+use mckerel_protocol::clientbound::play::{self, MapData, MapIcon};
+use mckerel_protocol::versions::{self, StateKind};
+
+fn write<P: Serialize<Value = P>>(packet: &P) -> Vec<u8> {
+    let mut written = Vec::new();
+    P::serialize(packet, &mut ByteWriter::new(&mut written));
+    written
+}
+
+#[test]
+fn registry() {
+    let protocols: Vec<_> = versions::supported().iter().map(|version| version.protocol).collect();
+    assert_eq!(protocols, [757, 756, 754]);
+    assert_eq!(versions::canonical().protocol, mckerel_protocol::packets::PROTOCOL_VERSION);
+    assert_eq!(versions::find(754).unwrap().name, "1.16.5");
+    assert!(versions::find(755).is_none());
+    assert_eq!(versions::unsupported_message(), "Unsupported version, please use 1.16.5 to 1.18.1");
+}
+
+#[test]
+fn canonical_version_is_untouched() {
+    let packet = write(&play::Packet::from(play::KeepAlive(5)));
+    let translated = versions::canonical().translate_clientbound(StateKind::Play, &packet).unwrap();
+    assert_eq!(&*translated, &packet[..]);
+    let v1_17_1 = versions::find(756).unwrap();
+    assert_eq!(&*v1_17_1.translate_clientbound(StateKind::Play, &packet).unwrap(), &packet[..]);
+}
+
+#[test]
+fn renumbered_packets() {
+    let v1_16_5 = versions::find(754).unwrap();
+
+    // keep alive is 0x21 now and was 0x1f
+    let packet = write(&play::Packet::from(play::KeepAlive(5)));
+    let translated = v1_16_5.translate_clientbound(StateKind::Play, &packet).unwrap();
+    assert_eq!(translated[0], 0x1f);
+    assert_eq!(translated[1..], packet[1..]);
+
+    // and 0x10 serverbound, instead of 0x0f
+    let mut wire = vec![0x10];
+    wire.extend_from_slice(&7i64.to_be_bytes());
+    let translated = v1_16_5.translate_serverbound(StateKind::Play, &wire).unwrap();
+    match mckerel_protocol::serverbound::play::Packet::deserialize(&mut ByteReader::new(&translated)).unwrap() {
+        mckerel_protocol::serverbound::play::Packet::KeepAlive(keep_alive) => assert_eq!(keep_alive.0, 7),
+        _ => panic!("wrong packet"),
+    }
+
+    // ids are only unique within a state
+    let status_pong = [0x01, 0, 0, 0, 0, 0, 0, 0, 1];
+    assert_eq!(&*v1_16_5.translate_clientbound(StateKind::Status, &status_pong).unwrap(), &status_pong[..]);
+}
+
+#[test]
+fn changed_layout() {
+    let v1_16_5 = versions::find(754).unwrap();
+    let map = MapData {
+        map_id: 1, scale: 2, locked: true, tracking_position: false, icons: None,
+        columns: 0, rows: None, x: None, z: None, data: None,
+    };
+    let packet = write(&play::Packet::from(map));
+    let translated = v1_16_5.translate_clientbound(StateKind::Play, &packet).unwrap();
+    // tracking position before locked, and an empty icon list
+    assert_eq!(&*translated, [0x25, 1, 2, 0, 1, 0, 0]);
+
+    let map = MapData {
+        map_id: 1, scale: 2, locked: false, tracking_position: true,
+        icons: Some(vec![MapIcon { kind: 3, x: 4, z: 5, direction: 6, display_name: None }]),
+        columns: 0, rows: None, x: None, z: None, data: None,
+    };
+    let packet = write(&play::Packet::from(map));
+    let translated = v1_16_5.translate_clientbound(StateKind::Play, &packet).unwrap();
+    let mut expected = vec![0x25, 1, 2, 1, 0, 1];
+    VarInt::serialize(&3, &mut ByteWriter::new(&mut expected));
+    expected.extend_from_slice(&[4, 5, 6, 0, 0]);
+    assert_eq!(&*translated, &expected[..]);
+}
+
+#[test]
+fn missing_packets() {
+    let chunk = write(&play::Packet::from(mckerel_protocol::states::play::empty_chunk(0, 0, &Default::default())));
+    for protocol in [756, 754] {
+        let err = versions::find(protocol).unwrap().translate_clientbound(StateKind::Play, &chunk).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::NotInVersion);
+        assert_eq!(err.packet(), Some(("ChunkData", 0x22)));
+    }
+    assert!(versions::canonical().translate_clientbound(StateKind::Play, &chunk).is_ok());
+}
+
+#[test]
+fn renumbered_away_ids() {
+    let v1_16_5 = versions::find(754).unwrap();
+    // query entity nbt and generate structure, which 1.18.1 gives to interact entity and keep alive
+    for id in [0x0d, 0x0f] {
+        let mut wire = vec![id];
+        wire.extend_from_slice(&7i64.to_be_bytes());
+        let err = v1_16_5.translate_serverbound(StateKind::Play, &wire).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::BadPacketId(id as i32));
+    }
+    // packets that kept their id still go through as they are
+    let teleport_confirm = [0x00, 0x00];
+    assert_eq!(&*v1_16_5.translate_serverbound(StateKind::Play, &teleport_confirm).unwrap(), &teleport_confirm[..]);
+}